@@ -0,0 +1,56 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A source of the current time, so time dependent logic does not have to
+/// read the system clock directly and can be driven deterministically
+pub trait Clock: Send + Sync {
+    /// The current instant, according to this clock
+    fn now(&self) -> Instant;
+
+    /// The time that has passed since the given instant, according to this clock
+    fn elapsed_since(&self, instant: Instant) -> Duration {
+        self.now().saturating_duration_since(instant)
+    }
+}
+
+/// The clock backed by the system's monotonic clock
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock which only moves when it is explicitly advanced
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(Instant::now())
+        }
+    }
+
+    /// Move the clock forward by the given duration
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+
+        *now += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
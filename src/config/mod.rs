@@ -1,4 +1,7 @@
 use std::marker::PhantomData;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
 
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::SeqNo;
@@ -17,7 +20,9 @@ use atlas_execution::serialize::ApplicationData;
 use atlas_execution::state::divisible_state::DivisibleState;
 use atlas_execution::state::monolithic_state::MonolithicState;
 
+use crate::clock::{Clock, SystemClock};
 use crate::persistent_log::SMRPersistentLog;
+use crate::server::follower_handling::{ForwardSkipObserver, LeaderSchedule};
 
 pub struct MonolithicStateReplicaConfig<RF, S, A, OP, ST, LT, NT, PL>
     where RF: ReconfigurationProtocol + 'static,
@@ -54,6 +59,26 @@ pub struct DivisibleStateReplicaConfig<RF, S, A, OP, ST, LT, NT, PL>
 
     /// The configuration for the State transfer protocol
     pub st_config: ST::Config,
+
+    /// How to handle checkpoints that arrive from the application out of sequence order
+    pub checkpoint_order_policy: CheckpointOrderPolicy,
+
+    /// The maximum amount of checkpoints processed in each iteration of the replica,
+    /// so a flood of checkpoints can't starve the protocols. The remaining checkpoints
+    /// are left for the following iterations. If `None`, all available checkpoints are processed
+    pub max_checkpoints_per_iteration: Option<NonZeroUsize>,
+}
+
+/// How a replica handles checkpoints which are received from the application
+/// with a lower sequence number than one it has already processed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CheckpointOrderPolicy {
+    /// Checkpoints older than the latest one we have processed are discarded
+    #[default]
+    Reject,
+    /// All of the checkpoints that are available are processed in sequence order.
+    /// Checkpoints older than the latest one we have already processed are still discarded
+    Reorder,
 }
 
 /// Represents a configuration used to bootstrap a `Replica`.
@@ -84,6 +109,21 @@ pub struct ReplicaConfig<RF, S, D, OP, ST, LT, NT, PL> where
     /// The path to the database
     pub db_path: String,
 
+    /// The minimum interval between two requests for the latest state.
+    /// Requests made within this interval of the previous one are deferred
+    /// until it elapses, so rapid triggers don't thrash the network
+    pub min_state_request_interval: Duration,
+
+    /// The maximum random delay applied before requesting the latest state.
+    /// When many replicas fall behind at once (for example, after a partition heals),
+    /// this spreads their requests instead of having them all hit the serving
+    /// replicas at the same time. A zero duration disables the delay
+    pub max_state_request_jitter: Duration,
+
+    /// The clock used by the time dependent logic of the replica, such as
+    /// the throttling of the requests for the latest state
+    pub clock: Arc<dyn Clock>,
+
     /// The configuration for the ordering protocol
     pub op_config: OP::Config,
 
@@ -100,4 +140,85 @@ pub struct ReplicaConfig<RF, S, D, OP, ST, LT, NT, PL> where
     pub reconfig_node: RF::Config,
 
     pub p: PhantomData<S>,
-}
\ No newline at end of file
+}
+
+/// The configuration for the follower handling thread of a replica
+pub struct FollowerHandlingConfig {
+    /// Is follower handling enabled? When it isn't, nothing is forwarded to followers:
+    /// the events delivered by the ordering protocol are discarded, and the other
+    /// handles don't deliver anything
+    pub enabled: bool,
+    /// Should the leader also take part in forwarding pre prepares to followers?
+    /// By default, the leader is exempt in order to save bandwidth as it already has
+    /// to send the pre prepare to all of the replicas
+    pub leader_forwards_preprepares: bool,
+    /// The maximum amount of followers this replica will forward pre prepares to.
+    /// If the routing assigns more followers than this, the excess is not served
+    pub max_followers_per_replica: Option<usize>,
+    /// Should we hold our prepares and commits for a given sequence until that sequence
+    /// has been committed locally, forwarding them all together?
+    /// This trades follower latency for not forwarding messages of sequences that never commit
+    pub forward_only_after_commit: bool,
+    /// Should we warn when a view does not have enough replicas to provide
+    /// f + 1 distinct forwarders to each follower?
+    pub warn_on_insufficient_forwarders: bool,
+    /// An optional leader schedule that received pre prepares are validated against
+    /// before being forwarded. Pre prepares which are off schedule are not forwarded
+    pub leader_schedule: Option<Box<dyn LeaderSchedule>>,
+    /// Should only an elected subset of f + 1 replicas forward sync (view change)
+    /// messages to followers? By default, every replica forwards them
+    pub elect_sync_forwarders: bool,
+    /// Should the elected sync forwarders rotate with each view, so that no replica
+    /// is always burdened with forwarding sync messages? Only relevant when electing them
+    pub rotate_sync_forwarders: bool,
+    /// How long a follower can go without sending us a heartbeat before it is suspected
+    /// of having gone silent. If `None`, followers are never evicted for being silent
+    pub follower_liveness_interval: Option<Duration>,
+    /// How long a suspected follower has to remain silent (after being suspected)
+    /// before it is evicted. Heartbeats received during this period clear the suspicion
+    pub follower_eviction_grace: Duration,
+    /// How many undeliverable forwards are kept for diagnostics. When full,
+    /// the oldest ones are dropped
+    pub dead_letter_capacity: usize,
+    /// The clock used by the time dependent logic of the follower handling
+    pub clock: Arc<dyn Clock>,
+    /// The amount of queued events above which the follower handling thread sheds
+    /// low priority forwards (prepares) in order to drain faster and stop blocking
+    /// the ordering protocol. If `None`, nothing is ever shed
+    pub shed_prepares_above: Option<usize>,
+    /// A soft cap on the bytes of messages the follower handling thread retains, cloned
+    /// into pending batches (messages held until commit are shared, so they don't count).
+    /// Above it, the thread stops taking new events until the retained messages are
+    /// delivered, pushing back on its input instead of allocating further. If `None`, there is no cap
+    pub max_retained_clone_bytes: Option<usize>,
+    /// An optional observer which is told about every message we decide not to forward,
+    /// along with the reason why
+    pub skip_observer: Option<Box<dyn ForwardSkipObserver>>,
+    /// Should a panic while handling an event be contained to that event?
+    /// When enabled, the offending event is logged and dropped and the follower
+    /// handling carries on. Otherwise, the panic brings down the follower handling thread
+    pub isolate_event_panics: bool,
+}
+
+impl Default for FollowerHandlingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            leader_forwards_preprepares: false,
+            max_followers_per_replica: None,
+            forward_only_after_commit: false,
+            warn_on_insufficient_forwarders: true,
+            leader_schedule: None,
+            elect_sync_forwarders: false,
+            rotate_sync_forwarders: true,
+            follower_liveness_interval: None,
+            follower_eviction_grace: Duration::from_secs(5),
+            dead_letter_capacity: 128,
+            clock: Arc::new(SystemClock),
+            shed_prepares_above: None,
+            max_retained_clone_bytes: None,
+            skip_observer: None,
+            isolate_event_panics: false,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod server;
 pub mod config;
 pub mod metric;
+pub mod clock;
 mod persistent_log;
 //pub mod follower;
\ No newline at end of file
@@ -43,6 +43,37 @@ pub const REPLICA_ORDERED_RQS_PROCESSED_ID: usize = 513;
 pub const LOG_TRANSFER_PROCESS_TIME: &str = "LOG_TRANSFER_PROCESS_TIME";
 pub const LOG_TRANSFER_PROCESS_TIME_ID: usize = 514;
 
+pub const STATE_TRANSFER_POLL_TIME: &str = "STATE_TRANSFER_POLL_TIME";
+pub const STATE_TRANSFER_POLL_TIME_ID: usize = 515;
+
+pub const FOLLOWER_TARGETS_OVERFLOW: &str = "FOLLOWER_TARGETS_OVERFLOW";
+pub const FOLLOWER_TARGETS_OVERFLOW_ID: usize = 516;
+
+pub const FOLLOWER_INSUFFICIENT_FORWARDERS: &str = "FOLLOWER_INSUFFICIENT_FORWARDERS";
+pub const FOLLOWER_INSUFFICIENT_FORWARDERS_ID: usize = 517;
+
+pub const FOLLOWER_FORWARD_CLONE_TIME: &str = "FOLLOWER_FORWARD_CLONE_TIME";
+pub const FOLLOWER_FORWARD_CLONE_TIME_ID: usize = 518;
+
+pub const FOLLOWER_DEAD_LETTERS_DROPPED: &str = "FOLLOWER_DEAD_LETTERS_DROPPED";
+pub const FOLLOWER_DEAD_LETTERS_DROPPED_ID: usize = 519;
+
+pub const FOLLOWER_MAX_SEQUENCE_LAG: &str = "FOLLOWER_MAX_SEQUENCE_LAG";
+pub const FOLLOWER_MAX_SEQUENCE_LAG_ID: usize = 520;
+
+pub const FOLLOWER_SHED_FORWARDS: &str = "FOLLOWER_SHED_FORWARDS";
+pub const FOLLOWER_SHED_FORWARDS_ID: usize = 521;
+
+pub const FOLLOWER_EVENT_PANICS: &str = "FOLLOWER_EVENT_PANICS";
+pub const FOLLOWER_EVENT_PANICS_ID: usize = 522;
+
+/// Stored in hundredths of a forwarder
+pub const FOLLOWER_MIN_OBSERVED_COVERAGE: &str = "FOLLOWER_MIN_OBSERVED_COVERAGE";
+pub const FOLLOWER_MIN_OBSERVED_COVERAGE_ID: usize = 523;
+
+pub const STATE_TRANSFER_MESSAGE_PROCESS_TIME: &str = "STATE_TRANSFER_MESSAGE_PROCESS_TIME";
+pub const STATE_TRANSFER_MESSAGE_PROCESS_TIME_ID: usize = 524;
+
 pub fn metrics() -> Vec<MetricRegistry> {
 
     vec![
@@ -59,6 +90,16 @@ pub fn metrics() -> Vec<MetricRegistry> {
         (REPLICA_TAKE_FROM_NETWORK_ID, REPLICA_TAKE_FROM_NETWORK.to_string(), MetricKind::Duration, MetricLevel::Trace).into(),
         (REPLICA_ORDERED_RQS_PROCESSED_ID, REPLICA_ORDERED_RQS_PROCESSED.to_string(), MetricKind::Counter, MetricLevel::Trace).into(),
         (LOG_TRANSFER_PROCESS_TIME_ID, LOG_TRANSFER_PROCESS_TIME.to_string(), MetricKind::Duration, MetricLevel::Debug).into(),
+        (STATE_TRANSFER_POLL_TIME_ID, STATE_TRANSFER_POLL_TIME.to_string(), MetricKind::Duration, MetricLevel::Debug).into(),
+        (FOLLOWER_TARGETS_OVERFLOW_ID, FOLLOWER_TARGETS_OVERFLOW.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
+        (FOLLOWER_INSUFFICIENT_FORWARDERS_ID, FOLLOWER_INSUFFICIENT_FORWARDERS.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
+        (FOLLOWER_FORWARD_CLONE_TIME_ID, FOLLOWER_FORWARD_CLONE_TIME.to_string(), MetricKind::Duration, MetricLevel::Debug).into(),
+        (FOLLOWER_DEAD_LETTERS_DROPPED_ID, FOLLOWER_DEAD_LETTERS_DROPPED.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
+        (FOLLOWER_MAX_SEQUENCE_LAG_ID, FOLLOWER_MAX_SEQUENCE_LAG.to_string(), MetricKind::Count, MetricLevel::Info).into(),
+        (FOLLOWER_SHED_FORWARDS_ID, FOLLOWER_SHED_FORWARDS.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
+        (FOLLOWER_EVENT_PANICS_ID, FOLLOWER_EVENT_PANICS.to_string(), MetricKind::Counter, MetricLevel::Info).into(),
+        (FOLLOWER_MIN_OBSERVED_COVERAGE_ID, FOLLOWER_MIN_OBSERVED_COVERAGE.to_string(), MetricKind::Count, MetricLevel::Info).into(),
+        (STATE_TRANSFER_MESSAGE_PROCESS_TIME_ID, STATE_TRANSFER_MESSAGE_PROCESS_TIME.to_string(), MetricKind::Duration, MetricLevel::Debug).into(),
     ]
 
 }
\ No newline at end of file
@@ -0,0 +1,237 @@
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::warn;
+
+use atlas_common::channel::ChannelSyncRx;
+use atlas_common::ordering::{Orderable, SeqNo};
+
+use crate::config::CheckpointOrderPolicy;
+
+/// A cloneable handle to pause and resume the intake of application checkpoints
+/// by a [DivStReplica](super::DivStReplica).
+///
+/// While paused, checkpoints accumulate in the channel from the application (pushing
+/// back on it once full), while the replica keeps running the ordering and state
+/// transfer protocols as usual
+#[derive(Clone)]
+pub struct CheckpointIntakeHandle {
+    paused: Arc<AtomicBool>,
+}
+
+impl CheckpointIntakeHandle {
+    /// Stop taking checkpoints from the application
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Resume taking checkpoints from the application
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Is the checkpoint intake paused?
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+}
+
+/// Decides which of the checkpoints delivered by the application are processed
+/// in each iteration of the replica, and in what order
+pub(super) struct CheckpointIntake {
+    /// How to handle checkpoints that arrive out of sequence order
+    order_policy: CheckpointOrderPolicy,
+    /// The maximum amount of checkpoints we take per iteration
+    max_per_iteration: usize,
+    /// The sequence number of the latest checkpoint we have processed
+    last_checkpoint: Option<SeqNo>,
+    /// Controls whether we are taking checkpoints from the application
+    handle: CheckpointIntakeHandle,
+}
+
+impl CheckpointIntake {
+    pub(super) fn new(order_policy: CheckpointOrderPolicy, max_per_iteration: Option<NonZeroUsize>) -> Self {
+        Self {
+            order_policy,
+            max_per_iteration: max_per_iteration.map_or(usize::MAX, NonZeroUsize::get),
+            last_checkpoint: None,
+            handle: CheckpointIntakeHandle { paused: Arc::new(AtomicBool::new(false)) },
+        }
+    }
+
+    pub(super) fn handle(&self) -> CheckpointIntakeHandle {
+        self.handle.clone()
+    }
+
+    /// Take the checkpoints to process in this iteration from the application, in the
+    /// order they should be processed. Nothing is taken while the intake is paused
+    pub(super) fn take<C: Orderable>(&self, checkpoint_rx: &ChannelSyncRx<C>) -> Vec<C> {
+        let mut checkpoints = Vec::new();
+
+        if self.handle.is_paused() {
+            return checkpoints;
+        }
+
+        while checkpoints.len() < self.max_per_iteration {
+            match checkpoint_rx.try_recv() {
+                Ok(checkpoint) => checkpoints.push(checkpoint),
+                Err(_) => break
+            }
+        }
+
+        if self.order_policy == CheckpointOrderPolicy::Reorder {
+            checkpoints.sort_by_key(|checkpoint| checkpoint.sequence_number());
+        }
+
+        checkpoints
+    }
+
+    /// Should the checkpoint with the given sequence number be processed? It isn't if it is
+    /// older than the latest checkpoint we have processed, as installing it would regress the state
+    pub(super) fn should_process(&self, seq_no: SeqNo) -> bool {
+        match self.last_checkpoint {
+            Some(last_checkpoint) if seq_no <= last_checkpoint => {
+                warn!("Received checkpoint {:?} out of order, as we have already processed checkpoint {:?}. Discarding it",
+                    seq_no, last_checkpoint);
+
+                false
+            }
+            _ => true
+        }
+    }
+
+    /// Mark the checkpoint with the given sequence number as processed
+    pub(super) fn processed(&mut self, seq_no: SeqNo) {
+        self.last_checkpoint = Some(seq_no);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atlas_common::channel;
+    use atlas_common::channel::ChannelSyncTx;
+
+    use super::*;
+
+    struct Checkpoint(SeqNo);
+
+    impl Orderable for Checkpoint {
+        fn sequence_number(&self) -> SeqNo {
+            self.0
+        }
+    }
+
+    fn deliver(tx: &ChannelSyncTx<Checkpoint>, seqs: &[u32]) {
+        for seq in seqs {
+            tx.send(Checkpoint(SeqNo::from(*seq))).unwrap();
+        }
+    }
+
+    /// Take the checkpoints of an iteration, processing those that should be
+    fn iterate(intake: &mut CheckpointIntake, rx: &ChannelSyncRx<Checkpoint>) -> Vec<SeqNo> {
+        let mut processed = Vec::new();
+
+        for checkpoint in intake.take(rx) {
+            let seq_no = checkpoint.sequence_number();
+
+            if intake.should_process(seq_no) {
+                intake.processed(seq_no);
+
+                processed.push(seq_no);
+            }
+        }
+
+        processed
+    }
+
+    fn seqs(seqs: &[u32]) -> Vec<SeqNo> {
+        seqs.iter().copied().map(SeqNo::from).collect()
+    }
+
+    #[test]
+    fn at_most_the_cap_is_taken_per_iteration() {
+        let (tx, rx) = channel::new_bounded_sync(16);
+        let mut intake = CheckpointIntake::new(CheckpointOrderPolicy::Reject, NonZeroUsize::new(3));
+
+        deliver(&tx, &[1, 2, 3, 4, 5, 6, 7]);
+
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[1, 2, 3]));
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[4, 5, 6]));
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[7]));
+        assert!(iterate(&mut intake, &rx).is_empty());
+    }
+
+    #[test]
+    fn without_a_cap_everything_available_is_taken() {
+        let (tx, rx) = channel::new_bounded_sync(16);
+        let mut intake = CheckpointIntake::new(CheckpointOrderPolicy::Reject, None);
+
+        deliver(&tx, &[1, 2, 3, 4, 5, 6, 7]);
+
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[1, 2, 3, 4, 5, 6, 7]));
+    }
+
+    #[test]
+    fn rejecting_discards_checkpoints_older_than_the_latest_processed() {
+        let (tx, rx) = channel::new_bounded_sync(16);
+        let mut intake = CheckpointIntake::new(CheckpointOrderPolicy::Reject, None);
+
+        deliver(&tx, &[2, 1, 3, 3]);
+
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[2, 3]));
+    }
+
+    #[test]
+    fn the_same_checkpoints_are_processed_differently_by_each_policy() {
+        let process = |policy| {
+            let (tx, rx) = channel::new_bounded_sync(16);
+            let mut intake = CheckpointIntake::new(policy, None);
+
+            deliver(&tx, &[3, 1, 2]);
+
+            iterate(&mut intake, &rx)
+        };
+
+        assert_eq!(process(CheckpointOrderPolicy::Reject), seqs(&[3]));
+        assert_eq!(process(CheckpointOrderPolicy::Reorder), seqs(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn reordering_processes_the_checkpoints_taken_in_sequence_order() {
+        let (tx, rx) = channel::new_bounded_sync(16);
+        let mut intake = CheckpointIntake::new(CheckpointOrderPolicy::Reorder, None);
+
+        deliver(&tx, &[3, 1, 2]);
+
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[1, 2, 3]));
+
+        //Checkpoints older than ones processed in earlier iterations are still discarded
+        deliver(&tx, &[2, 5, 4]);
+
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[4, 5]));
+    }
+
+    #[test]
+    fn nothing_is_taken_while_paused() {
+        let (tx, rx) = channel::new_bounded_sync(16);
+        let mut intake = CheckpointIntake::new(CheckpointOrderPolicy::Reject, None);
+        let handle = intake.handle();
+
+        deliver(&tx, &[1, 2]);
+
+        handle.pause();
+
+        assert!(iterate(&mut intake, &rx).is_empty());
+
+        //They accumulate in the channel in the meantime, pushing back on the application
+        deliver(&tx, &[3]);
+
+        assert_eq!(rx.len(), 3);
+
+        handle.resume();
+
+        assert_eq!(iterate(&mut intake, &rx), seqs(&[1, 2, 3]));
+        assert!(rx.is_empty());
+    }
+}
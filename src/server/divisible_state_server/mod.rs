@@ -22,6 +22,11 @@ use crate::config::DivisibleStateReplicaConfig;
 use crate::metric::RUN_LATENCY_TIME_ID;
 use crate::persistent_log::SMRPersistentLog;
 use crate::server::Replica;
+use crate::server::divisible_state_server::checkpoint_intake::CheckpointIntake;
+
+pub use crate::server::divisible_state_server::checkpoint_intake::CheckpointIntakeHandle;
+
+mod checkpoint_intake;
 
 pub struct DivStReplica<RP, SE, S, A, OP, ST, LT, NT, PL>
     where RP: ReconfigurationProtocol + 'static,
@@ -38,6 +43,8 @@ pub struct DivStReplica<RP, SE, S, A, OP, ST, LT, NT, PL>
 
     state_tx: ChannelSyncTx<InstallStateMessage<S>>,
     checkpoint_rx: ChannelSyncRx<AppStateMessage<S>>,
+    /// Decides which of the checkpoints from the application we process
+    checkpoint_intake: CheckpointIntake,
     /// State transfer protocols
     state_transfer_protocol: ST,
 }
@@ -54,7 +61,7 @@ impl<RP, SE, S, A, OP, ST, LT, NT, PL> DivStReplica<RP, SE, S, A, OP, ST, LT, NT
     NT: SMRNetworkNode<RP::InformationProvider, RP::Serialization, A::AppData, OP::Serialization, ST::Serialization, LT::Serialization> + 'static, {
     pub async fn bootstrap(cfg: DivisibleStateReplicaConfig<RP, S, A, OP, ST, LT, NT, PL>) -> Result<Self> {
         let DivisibleStateReplicaConfig {
-            service, replica_config, st_config
+            service, replica_config, st_config, checkpoint_order_policy, max_checkpoints_per_iteration
         } = cfg;
 
         let (executor_handle, executor_receiver) = SE::init_handle();
@@ -77,6 +84,7 @@ impl<RP, SE, S, A, OP, ST, LT, NT, PL> DivStReplica<RP, SE, S, A, OP, ST, LT, NT
             inner_replica,
             state_tx,
             checkpoint_rx,
+            checkpoint_intake: CheckpointIntake::new(checkpoint_order_policy, max_checkpoints_per_iteration),
             state_transfer_protocol,
         };
 
@@ -85,6 +93,12 @@ impl<RP, SE, S, A, OP, ST, LT, NT, PL> DivStReplica<RP, SE, S, A, OP, ST, LT, NT
         Ok(replica)
     }
 
+    /// A handle to pause the intake of application checkpoints, for example to avoid
+    /// contention during a large incoming state transfer
+    pub fn checkpoint_intake_handle(&self) -> CheckpointIntakeHandle {
+        self.checkpoint_intake.handle()
+    }
+
     pub fn run(&mut self) -> Result<()> {
         let mut last_loop = Instant::now();
 
@@ -100,17 +114,28 @@ impl<RP, SE, S, A, OP, ST, LT, NT, PL> DivStReplica<RP, SE, S, A, OP, ST, LT, NT
     }
 
     fn receive_checkpoints(&mut self) -> Result<()> {
-        while let Ok(checkpoint) = self.checkpoint_rx.try_recv() {
-            let seq_no = checkpoint.sequence_number();
+        for checkpoint in self.checkpoint_intake.take(&self.checkpoint_rx) {
+            if self.checkpoint_intake.should_process(checkpoint.sequence_number()) {
+                self.process_checkpoint(checkpoint)?;
+            }
+        }
 
-            let (descriptor, state_parts) = checkpoint.into_state();
+        Ok(())
+    }
 
-            let current_view = self.inner_replica.ordering_protocol.view();
+    /// Pass a checkpoint on to the state transfer and ordering protocols
+    fn process_checkpoint(&mut self, checkpoint: AppStateMessage<S>) -> Result<()> {
+        let seq_no = checkpoint.sequence_number();
 
-            self.state_transfer_protocol.handle_state_received_from_app(current_view, descriptor, state_parts)?;
+        let (descriptor, state_parts) = checkpoint.into_state();
 
-            self.inner_replica.ordering_protocol.checkpointed(seq_no)?;
-        }
+        let current_view = self.inner_replica.ordering_protocol.view();
+
+        self.state_transfer_protocol.handle_state_received_from_app(current_view, descriptor, state_parts)?;
+
+        self.inner_replica.ordering_protocol.checkpointed(seq_no)?;
+
+        self.checkpoint_intake.processed(seq_no);
 
         Ok(())
     }
@@ -1,19 +1,830 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::marker::PhantomData;
 use std::ops::Deref;
+use std::panic::{AssertUnwindSafe, catch_unwind};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use log::{debug, error, info, warn};
 use atlas_common::channel;
-use atlas_common::channel::{ChannelSyncRx, ChannelSyncTx};
+use atlas_common::channel::{ChannelSyncRx, ChannelSyncTx, TryRecvError};
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
 use atlas_common::globals::ReadOnly;
 use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_communication::message::{NetworkMessageKind, StoredMessage, System};
 use atlas_communication::protocol_node::ProtocolNetworkNode;
 use atlas_execution::app::{Request};
 use atlas_execution::serialize::ApplicationData;
-use atlas_core::followers::{FollowerChannelMsg, FollowerEvent, FollowerHandle};
+use atlas_core::followers::{FollowerEvent, FollowerHandle};
 use atlas_core::log_transfer::networking::serialize::LogTransferMessage;
 use atlas_core::messages::{Protocol, SystemMessage};
 use atlas_core::ordering_protocol::networking::serialize::{NetworkView, OrderingProtocolMessage, PermissionedOrderingProtocolMessage};
 use atlas_core::serialize::Service;
 use atlas_core::state_transfer::networking::serialize::StateTransferMessage;
+use atlas_metrics::metrics::{metric_duration, metric_increment, metric_store_count};
+
+use crate::config::FollowerHandlingConfig;
+use crate::server::follower_handling::routing::{ForwardSkipReason, RoutingTable, RoutingTrace};
+use crate::metric::{FOLLOWER_DEAD_LETTERS_DROPPED_ID, FOLLOWER_EVENT_PANICS_ID, FOLLOWER_MIN_OBSERVED_COVERAGE_ID, FOLLOWER_FORWARD_CLONE_TIME_ID, FOLLOWER_INSUFFICIENT_FORWARDERS_ID, FOLLOWER_MAX_SEQUENCE_LAG_ID, FOLLOWER_SHED_FORWARDS_ID, FOLLOWER_TARGETS_OVERFLOW_ID};
+
+pub mod routing;
+#[cfg(test)]
+mod testing;
+
+/// How long the follower handling thread waits for new events before
+/// checking whether there are pending batches that have to be flushed
+const FOLLOWER_THREAD_WAIT_TIME: Duration = Duration::from_millis(50);
+
+const FOLLOWER_CHANNEL_SIZE: usize = 1024;
+/// The maximum amount of backlogged events we take from the channel at once
+/// in order to forward them in sequence order
+const FOLLOWER_BACKLOG_DRAIN: usize = FOLLOWER_CHANNEL_SIZE;
+const FOLLOWER_CONTROL_CHANNEL_SIZE: usize = 128;
+/// How many views we keep the routing digests of
+const ROUTING_DIGEST_HISTORY: usize = 32;
+
+/// The schedule of leaders, for deployments where the leader rotates.
+/// Used to validate that the sequence of a received pre prepare falls within the range
+/// assigned to the leader which sent it
+pub trait LeaderSchedule: Send {
+    /// Is the given leader scheduled to propose the given sequence number?
+    fn is_scheduled(&self, leader: NodeId, seq: SeqNo) -> bool;
+}
+
+/// The kinds of messages that are forwarded to followers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForwardKind {
+    PrePrepare,
+    Prepare,
+    Commit,
+    Sync,
+}
+
+/// The phase of the consensus a message handed to us by the ordering protocol belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsensusPhase {
+    PrePrepare,
+    Prepare,
+    Commit,
+}
+
+/// Tells the consensus phase of the messages handed to the follower handling
+pub type ConsensusClassifier<M> = Box<dyn Fn(&M) -> ConsensusPhase + Send>;
+
+/// The messages of the ordering protocol, as they are forwarded to the followers
+pub type ProtocolForward<D, OP> = StoredMessage<Protocol<<OP as OrderingProtocolMessage<D>>::ProtocolMessage>>;
+
+/// An event handed to the follower handling, reduced to what it needs to route it
+pub enum FollowerInput<V, M> {
+    /// A consensus message, along with the view it belongs to
+    Consensus(V, Arc<ReadOnly<M>>),
+    /// A sync (view change) message
+    Sync(Arc<ReadOnly<M>>),
+}
+
+/// The events the follower handling can be fed with
+pub trait IntoFollowerInput: Send + 'static {
+    /// The view the consensus messages belong to
+    type View: NetworkView + Orderable + Clone + Send + 'static;
+    /// The messages which are forwarded to the followers
+    type Message: ForwardedMessage;
+
+    fn into_input(self) -> FollowerInput<Self::View, Self::Message>;
+}
+
+impl<V, M> IntoFollowerInput for FollowerInput<V, M>
+    where V: NetworkView + Orderable + Clone + Send + 'static,
+          M: ForwardedMessage {
+    type View = V;
+    type Message = M;
+
+    fn into_input(self) -> FollowerInput<V, M> {
+        self
+    }
+}
+
+impl<D, OP, POP> IntoFollowerInput for FollowerEvent<D, OP, POP>
+    where D: 'static,
+          OP: OrderingProtocolMessage<D> + 'static,
+          POP: PermissionedOrderingProtocolMessage + 'static {
+    type View = POP::ViewInfo;
+    type Message = ProtocolForward<D, OP>;
+
+    fn into_input(self) -> FollowerInput<Self::View, Self::Message> {
+        match self {
+            FollowerEvent::ReceivedConsensusMsg(view, consensus_msg) => FollowerInput::Consensus(view, consensus_msg),
+            FollowerEvent::ReceivedViewChangeMsg(view_change_msg) => FollowerInput::Sync(view_change_msg),
+        }
+    }
+}
+
+/// A message the follower handling forwards to followers
+pub trait ForwardedMessage: Send + Sync + 'static {
+    /// The replica which sent the message
+    fn sender(&self) -> NodeId;
+
+    /// The sequence number of the message (a view number, for sync messages)
+    fn seq(&self) -> SeqNo;
+
+    /// The length of the payload of the message, in bytes
+    fn payload_len(&self) -> usize;
+
+    /// Clone the message into a copy which can be handed to the transport
+    fn clone_message(&self) -> Self;
+}
+
+impl<P> ForwardedMessage for StoredMessage<Protocol<P>>
+    where P: Orderable + Clone + Send + Sync + 'static {
+    fn sender(&self) -> NodeId {
+        self.header().from()
+    }
+
+    fn seq(&self) -> SeqNo {
+        self.message().payload().sequence_number()
+    }
+
+    fn payload_len(&self) -> usize {
+        self.header().payload_length()
+    }
+
+    fn clone_message(&self) -> Self {
+        StoredMessage::new(self.header().clone(), self.message().clone())
+    }
+}
+
+/// How the follower handling delivers messages to the followers
+pub trait FollowerTransport<M>: Send + 'static {
+    /// Send a message to all of the given followers.
+    /// Returns the followers it could not be sent to
+    fn broadcast(&self, message: M, targets: Vec<NodeId>) -> std::result::Result<(), Vec<NodeId>>;
+
+    /// Send a message to a single follower
+    fn send(&self, message: M, target: NodeId, flush: bool) -> Result<()>;
+}
+
+/// Forwards the messages of the ordering protocol through the network node of the replica
+pub struct NodeTransport<D, OP, ST, LP, NT> {
+    node: Arc<NT>,
+    _phantom: PhantomData<fn() -> Service<D, OP, ST, LP>>,
+}
+
+impl<D, OP, ST, LP, NT> NodeTransport<D, OP, ST, LP, NT> {
+    pub fn new(node: Arc<NT>) -> Self {
+        Self { node, _phantom: Default::default() }
+    }
+}
+
+impl<D, OP, ST, LP, NT> FollowerTransport<ProtocolForward<D, OP>> for NodeTransport<D, OP, ST, LP, NT>
+    where D: ApplicationData + 'static,
+          OP: OrderingProtocolMessage<D> + 'static,
+          ST: StateTransferMessage + 'static,
+          LP: LogTransferMessage<D, OP> + 'static,
+          NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> + Send + Sync + 'static {
+    fn broadcast(&self, message: ProtocolForward<D, OP>, targets: Vec<NodeId>) -> std::result::Result<(), Vec<NodeId>> {
+        self.node.broadcast(SystemMessage::from_fwd_protocol_message(message), targets.into_iter())
+    }
+
+    fn send(&self, message: ProtocolForward<D, OP>, target: NodeId, flush: bool) -> Result<()> {
+        self.node.send(SystemMessage::from_fwd_protocol_message(message), target, flush)
+    }
+}
+
+/// Observes every message the follower handling decides not to forward, so all
+/// of the reasons for skipping a forward can be followed in a single place
+pub trait ForwardSkipObserver: Send {
+    /// A message of the given kind and sequence number was not forwarded
+    fn on_skip(&self, reason: ForwardSkipReason, seq: SeqNo, kind: ForwardKind);
+}
+
+/// The information available to a [FollowerTargetStrategy] when selecting targets
+pub struct TargetSelection<'a> {
+    /// Our own id
+    pub own_id: NodeId,
+    /// The followers that the fair split routing assigned to us (empty if we
+    /// are not part of the forwarding layout of the view)
+    pub assigned: &'a [NodeId],
+    /// All of the active followers, sorted
+    pub active_followers: &'a [NodeId],
+    /// The region each follower has been tagged with, if any
+    pub regions: &'a BTreeMap<NodeId, String>,
+}
+
+/// The strategy used to select which followers we forward pre prepares to.
+///
+/// Strategies can refine or replace the default assignment.
+/// Note that a strategy which does not cover every follower with f + 1 replicas
+/// loses the protection against message dropping
+pub trait FollowerTargetStrategy: Send {
+    /// Select the followers to forward the pre prepare to
+    fn select_targets(&self, selection: &TargetSelection) -> Vec<NodeId>;
+}
+
+/// The default strategy, which forwards to the followers assigned by the fair split routing
+pub struct FairSplitStrategy;
+
+impl FollowerTargetStrategy for FairSplitStrategy {
+    fn select_targets(&self, selection: &TargetSelection) -> Vec<NodeId> {
+        selection.assigned.to_vec()
+    }
+}
+
+/// A strategy which forwards to every active follower, regardless of the routing
+pub struct ForwardToAllStrategy;
+
+impl FollowerTargetStrategy for ForwardToAllStrategy {
+    fn select_targets(&self, selection: &TargetSelection) -> Vec<NodeId> {
+        selection.active_followers.to_vec()
+    }
+}
+
+/// A strategy which forwards to the followers assigned by the fair split routing, plus
+/// one follower of every region the assignment does not cover, so each of our forwards
+/// reaches all regions. Untagged followers are not considered part of any region.
+///
+/// The extra follower of each region is picked based on our id, so the extra load is
+/// spread over the followers of the region instead of always falling on the same one
+pub struct RegionCoverageStrategy;
+
+impl FollowerTargetStrategy for RegionCoverageStrategy {
+    fn select_targets(&self, selection: &TargetSelection) -> Vec<NodeId> {
+        let mut targets = selection.assigned.to_vec();
+
+        let mut followers_by_region: BTreeMap<&String, Vec<NodeId>> = BTreeMap::new();
+
+        for follower in selection.active_followers {
+            if let Some(region) = selection.regions.get(follower) {
+                followers_by_region.entry(region).or_default().push(*follower);
+            }
+        }
+
+        for (region, followers) in followers_by_region {
+            let covered = targets.iter()
+                .any(|target| selection.regions.get(target) == Some(region));
+
+            if !covered {
+                targets.push(followers[selection.own_id.id() as usize % followers.len()]);
+            }
+        }
+
+        targets
+    }
+}
+
+/// The batching preference of a given follower.
+///
+/// Messages destined to a follower that batches are held until either
+/// `max_batch_size` messages are pending or the oldest pending message has
+/// been waiting for `max_linger`, at which point they are all delivered together
+#[derive(Clone, Copy, Debug)]
+pub struct FollowerBatchingPreference {
+    /// The maximum amount of messages that are delivered together
+    pub max_batch_size: usize,
+    /// The maximum amount of time a message can be held before being delivered
+    pub max_linger: Duration,
+}
+
+impl FollowerBatchingPreference {
+    /// Does this follower want its messages to be batched?
+    fn is_batching(&self) -> bool {
+        self.max_batch_size > 1
+    }
+}
+
+impl Default for FollowerBatchingPreference {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 1,
+            max_linger: Duration::ZERO,
+        }
+    }
+}
+
+/// All of the tunable controls of a follower, which can be replaced together
+#[derive(Clone, Debug, Default)]
+pub struct FollowerControls {
+    /// How the messages destined to the follower are batched
+    pub batching: FollowerBatchingPreference,
+    /// If set, the follower is quarantined for this long. Otherwise, any quarantine is lifted
+    pub quarantine: Option<Duration>,
+    /// The region the follower is located in, if any
+    pub region: Option<String>,
+}
+
+/// Administrative messages that can be delivered to the follower handling thread.
+/// These are kept separate from the [FollowerEvent]s, which are produced by the
+/// ordering protocol
+pub enum FollowerControlMsg {
+    /// Register a new follower, along with its batching preference
+    RegisterFollower(NodeId, FollowerBatchingPreference),
+    /// Remove a follower that is no longer following the quorum
+    RemoveFollower(NodeId),
+    /// We have received a heartbeat from a follower, so it is still alive
+    FollowerHeartbeat(NodeId),
+    /// A follower has acknowledged having received everything up to the given sequence
+    FollowerAcked(NodeId, SeqNo),
+    /// Tag a follower with the region it is located in
+    TagFollowerRegion(NodeId, String),
+    /// Replace all of the controls of a follower at once
+    UpdateFollowerControls(NodeId, FollowerControls),
+    /// Suspend all forwarding to a follower for the given duration, after which
+    /// it resumes automatically
+    QuarantineFollower(NodeId, Duration),
+    /// A sequence has been committed locally, so the prepares and commits that were
+    /// being held for it (and all previous sequences) can be forwarded
+    SequenceCommitted(SeqNo),
+    /// Query whether the given node is a registered follower
+    IsFollower(NodeId, ChannelSyncTx<bool>),
+    /// A follower has reported which replicas it received the pre prepare of a given sequence from
+    ForwardersObserved(NodeId, SeqNo, Vec<NodeId>),
+    /// Query the average amount of distinct forwarders each follower has observed per sequence
+    ObservedCoverage(ChannelSyncTx<BTreeMap<NodeId, f64>>),
+    /// Query how many sequences each follower is lagging behind what we have forwarded to it
+    FollowerLag(ChannelSyncTx<BTreeMap<NodeId, u32>>),
+    /// Query the highest sequence we have forwarded to any follower
+    HighestForwarded(ChannelSyncTx<Option<SeqNo>>),
+    /// Query the forwards which could not be delivered
+    DeadLetters(ChannelSyncTx<Vec<DeadLetter>>),
+    /// Query the followers we are failing to deliver messages to
+    FailingFollowers(ChannelSyncTx<BTreeMap<NodeId, usize>>),
+    /// Record the routing decision taken for the pre prepare with the given sequence number
+    TraceRouting(SeqNo),
+    /// Take the routing decision recorded for the given sequence number, if any
+    TakeRoutingTrace(SeqNo, ChannelSyncTx<Option<RoutingTrace>>),
+    /// Recompute the follower assignments for the latest view and the current follower set.
+    /// Responds with `None` if we don't know of any view yet
+    RecomputeAssignments(ChannelSyncTx<Option<RoutingTable>>),
+    /// Query how many events are waiting to be processed
+    PendingEvents(ChannelSyncTx<usize>),
+    /// Query whether we have processed any event within the given window
+    IsActive(Duration, ChannelSyncTx<bool>),
+    /// Query the digests of the routing tables we computed for the most recent views
+    RoutingDigests(ChannelSyncTx<BTreeMap<SeqNo, Digest>>),
+    /// Query the `(n, f)` of the latest view we have seen, which are the values
+    /// used to route pre prepares
+    QuorumParams(ChannelSyncTx<Option<(usize, usize)>>),
+    /// Replace the strategy used to select the followers we forward pre prepares to.
+    /// Takes effect from the next pre prepare
+    SetTargetStrategy(Box<dyn FollowerTargetStrategy>),
+    /// Stop forwarding messages, deliver whatever is still pending and exit the thread.
+    /// The provided channel is notified once the thread has stopped
+    Shutdown(ChannelSyncTx<()>),
+}
+
+/// A cloneable handle to deliver administrative messages to the follower handling thread.
+///
+/// When follower handling is disabled, there is no thread to deliver them to, so messages
+/// are dropped and queries answer as if no follower was ever registered
+pub struct FollowerControlHandle {
+    inner: Option<ChannelSyncTx<FollowerControlMsg>>,
+}
+
+impl FollowerControlHandle {
+    fn new(inner: ChannelSyncTx<FollowerControlMsg>) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Register a new follower with the given batching preference
+    pub fn register_follower(&self, follower: NodeId, preference: FollowerBatchingPreference) -> Result<()> {
+        self.send(FollowerControlMsg::RegisterFollower(follower, preference), "Failed to send follower registration to follower handling thread")
+    }
+
+    /// Remove a follower
+    pub fn remove_follower(&self, follower: NodeId) -> Result<()> {
+        self.send(FollowerControlMsg::RemoveFollower(follower), "Failed to send follower removal to follower handling thread")
+    }
+
+    /// Notify the follower handling thread that we have heard from a follower
+    pub fn follower_heartbeat(&self, follower: NodeId) -> Result<()> {
+        self.send(FollowerControlMsg::FollowerHeartbeat(follower), "Failed to send follower heartbeat to follower handling thread")
+    }
+
+    /// Notify the follower handling thread that a follower has acknowledged
+    /// everything up to the given sequence
+    pub fn follower_acked(&self, follower: NodeId, seq: SeqNo) -> Result<()> {
+        self.send(FollowerControlMsg::FollowerAcked(follower, seq), "Failed to send follower acknowledgement to follower handling thread")
+    }
+
+    /// Notify the follower handling thread of the replicas a follower has reported
+    /// receiving the pre prepare of a given sequence from
+    pub fn forwarders_observed(&self, follower: NodeId, seq: SeqNo, forwarders: Vec<NodeId>) -> Result<()> {
+        self.send(FollowerControlMsg::ForwardersObserved(follower, seq, forwarders), "Failed to send observed forwarders to follower handling thread")
+    }
+
+    /// The average amount of distinct replicas each follower has received pre prepares from,
+    /// per sequence, as reported by the followers. Followers averaging less than f + 1
+    /// are not getting the intended protection against message dropping
+    pub fn observed_coverage(&self) -> Result<BTreeMap<NodeId, f64>> {
+        self.query(FollowerControlMsg::ObservedCoverage)
+    }
+
+    /// Quarantine a follower that is suspected of misbehaving, suspending all forwarding
+    /// to it for the given duration ([Duration::MAX] quarantines it until it is lifted through
+    /// [FollowerControlHandle::update_follower_controls]). Quarantined followers don't count
+    /// towards the coverage of the pre prepare routing
+    pub fn quarantine_follower(&self, follower: NodeId, duration: Duration) -> Result<()> {
+        self.send(FollowerControlMsg::QuarantineFollower(follower, duration), "Failed to send follower quarantine to follower handling thread")
+    }
+
+    /// Notify the follower handling thread that a sequence has been committed locally
+    pub fn sequence_committed(&self, seq: SeqNo) -> Result<()> {
+        self.send(FollowerControlMsg::SequenceCommitted(seq), "Failed to send committed sequence to follower handling thread")
+    }
+
+    /// Tag a follower with the region it is located in, for region aware target strategies
+    /// (such as [RegionCoverageStrategy])
+    pub fn tag_follower_region(&self, follower: NodeId, region: String) -> Result<()> {
+        self.send(FollowerControlMsg::TagFollowerRegion(follower, region), "Failed to send follower region to follower handling thread")
+    }
+
+    /// Replace all of the controls of a follower in a single operation, so no forward
+    /// ever sees a mix of the old and new controls
+    pub fn update_follower_controls(&self, follower: NodeId, controls: FollowerControls) -> Result<()> {
+        self.send(FollowerControlMsg::UpdateFollowerControls(follower, controls), "Failed to send follower controls to follower handling thread")
+    }
+
+    /// Is the given node a registered follower?
+    pub fn is_follower(&self, node: NodeId) -> Result<bool> {
+        self.query(|response| FollowerControlMsg::IsFollower(node, response))
+    }
+
+    /// How many sequences each follower is lagging behind the latest sequence
+    /// we have forwarded to it
+    pub fn follower_lag(&self) -> Result<BTreeMap<NodeId, u32>> {
+        self.query(FollowerControlMsg::FollowerLag)
+    }
+
+    /// The highest consensus sequence the follower handling thread has forwarded to any follower
+    /// (sync messages, which carry view numbers, and directed forwards are not considered).
+    /// Compared with the acknowledgements of the followers, this reveals how far behind they are
+    pub fn highest_forwarded_seq(&self) -> Result<Option<SeqNo>> {
+        self.query(FollowerControlMsg::HighestForwarded)
+    }
+
+    /// The most recent forwards which could not be delivered to followers,
+    /// oldest first
+    pub fn dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        self.query(FollowerControlMsg::DeadLetters)
+    }
+
+    /// The followers the last delivery attempts to have failed, along with how many
+    /// consecutive failures we have had with each of them. Followers that keep failing
+    /// are candidates to be quarantined or removed
+    pub fn failing_followers(&self) -> Result<BTreeMap<NodeId, usize>> {
+        self.query(FollowerControlMsg::FailingFollowers)
+    }
+
+    /// Record the full routing decision for the pre prepare with the given sequence
+    /// number, once we receive it. Meant to debug why a follower did not receive a message
+    pub fn trace_routing(&self, seq: SeqNo) -> Result<()> {
+        self.send(FollowerControlMsg::TraceRouting(seq), "Failed to send routing trace request to follower handling thread")
+    }
+
+    /// Take the routing decision recorded for the given sequence number, if it
+    /// has been traced and the pre prepare has already been handled
+    pub fn take_routing_trace(&self, seq: SeqNo) -> Result<Option<RoutingTrace>> {
+        self.query(|response| FollowerControlMsg::TakeRoutingTrace(seq, response))
+    }
+
+    /// Force the follower assignments to be recomputed for the latest view and the current
+    /// follower set (for example, after changing the configuration), returning the new assignments
+    pub fn recompute_assignments(&self) -> Result<Option<RoutingTable>> {
+        self.query(FollowerControlMsg::RecomputeAssignments)
+    }
+
+    /// How many events from the ordering protocol are queued, waiting for the follower
+    /// handling thread. A steadily growing count means the thread is overloaded
+    pub fn pending_events(&self) -> Result<usize> {
+        self.query(FollowerControlMsg::PendingEvents)
+    }
+
+    /// Has the follower handling thread processed any event within the given window?
+    /// Since the thread answers this itself, a thread which is stuck won't answer at all,
+    /// which distinguishes it from one that is legitimately idle
+    pub fn is_active(&self, within: Duration) -> Result<bool> {
+        self.query(|response| FollowerControlMsg::IsActive(within, response))
+    }
+
+    /// The digests of the routing tables computed for the most recent views, which can
+    /// be compared with the ones of other replicas to confirm that they all agree
+    /// on the follower assignments
+    pub fn routing_digests(&self) -> Result<BTreeMap<SeqNo, Digest>> {
+        self.query(FollowerControlMsg::RoutingDigests)
+    }
+
+    /// The `(n, f)` of the latest view known to the follower handling thread,
+    /// if it has already seen one
+    pub fn current_quorum_params(&self) -> Result<Option<(usize, usize)>> {
+        self.query(FollowerControlMsg::QuorumParams)
+    }
+
+    /// Replace the strategy used to select the followers we forward pre prepares to,
+    /// without having to restart the follower handling
+    pub fn set_target_strategy(&self, strategy: Box<dyn FollowerTargetStrategy>) -> Result<()> {
+        self.send(FollowerControlMsg::SetTargetStrategy(strategy), "Failed to send target strategy to follower handling thread")
+    }
+
+    /// Stop the follower handling thread, blocking until it has delivered all of
+    /// the messages it still had pending and exited.
+    /// This should be done before tearing down any other subsystem of the replica,
+    /// so we don't attempt to forward messages through closed channels
+    pub fn shutdown(&self) -> Result<()> {
+        self.query(FollowerControlMsg::Shutdown)
+    }
+
+    /// Send a message to the follower handling thread
+    fn send(&self, message: FollowerControlMsg, error_msg: &'static str) -> Result<()> {
+        match &self.inner {
+            Some(inner) => inner.send(message).wrapped_msg(ErrorKind::CommunicationChannel, error_msg),
+            None => Ok(()),
+        }
+    }
+
+    /// Send a message to the follower handling thread and wait for its response
+    fn query<R: Default>(&self, message: impl FnOnce(ChannelSyncTx<R>) -> FollowerControlMsg) -> Result<R> {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return Ok(R::default()),
+        };
+
+        let (tx, rx) = channel::new_bounded_sync(1);
+
+        inner.send(message(tx))
+            .wrapped_msg(ErrorKind::CommunicationChannel, "Failed to send query to follower handling thread")?;
+
+        rx.recv().wrapped_msg(ErrorKind::CommunicationChannel, "Failed to receive response from follower handling thread")
+    }
+}
+
+impl Clone for FollowerControlHandle {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone()
+        }
+    }
+}
+
+/// A message to forward to an explicit set of followers, bypassing the routing
+struct DirectedForward<M> {
+    targets: Vec<NodeId>,
+    message: Arc<ReadOnly<M>>,
+    /// Responds with the targets which are not registered followers
+    response: ChannelSyncTx<Vec<NodeId>>,
+}
+
+/// Messages which affect how the follower handling thread forwards messages
+enum ForwardControlMsg<M, T> {
+    /// Forward a message to an explicit set of followers
+    Directed(DirectedForward<M>),
+    /// Replace the transport used to forward messages
+    ReplaceTransport(T),
+}
+
+/// A cloneable handle to control the forwarding of the follower handling thread: forwarding
+/// messages to an explicit subset of the followers instead of the ones selected by the
+/// routing (for testing or targeted deliveries) and replacing the transport used to forward.
+///
+/// When follower handling is disabled, nothing is forwarded
+pub struct FollowerForwardHandle<M, T> {
+    inner: Option<ChannelSyncTx<ForwardControlMsg<M, T>>>,
+}
+
+impl<M, T> FollowerForwardHandle<M, T> {
+    fn new(inner: ChannelSyncTx<ForwardControlMsg<M, T>>) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Replace the transport used to forward messages to followers (for example, after
+    /// the network configuration changes). Forwards that were already sent completed on
+    /// the previous transport, while every later send (including pending batches) uses the new one
+    pub fn replace_transport(&self, transport: T) -> Result<()> {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return Ok(()),
+        };
+
+        inner.send(ForwardControlMsg::ReplaceTransport(transport))
+            .wrapped_msg(ErrorKind::CommunicationChannel, "Failed to send replacement transport to follower handling thread")
+    }
+
+    /// Forward a message to the given followers, once each.
+    /// Every target has to be a registered follower, otherwise nothing is forwarded
+    /// and an error naming the targets which are not registered is returned.
+    /// Quarantined followers are still not forwarded anything
+    pub fn forward_to(&self, targets: Vec<NodeId>, message: Arc<ReadOnly<M>>) -> Result<()> {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            //Without follower handling, there is nothing to forward
+            None => return Ok(()),
+        };
+
+        let (tx, rx) = channel::new_bounded_sync(1);
+
+        inner.send(ForwardControlMsg::Directed(DirectedForward { targets, message, response: tx }))
+            .wrapped_msg(ErrorKind::CommunicationChannel, "Failed to send directed forward to follower handling thread")?;
+
+        let unregistered: Vec<NodeId> = rx.recv()
+            .wrapped_msg(ErrorKind::CommunicationChannel, "Failed to receive response from follower handling thread")?;
+
+        if !unregistered.is_empty() {
+            return Err(format!("Refused directed forward, as {:?} are not registered followers", unregistered))
+                .wrapped(ErrorKind::CoreServer);
+        }
+
+        Ok(())
+    }
+}
+
+impl<D, OP, ST, LP, NT> FollowerForwardHandle<ProtocolForward<D, OP>, NodeTransport<D, OP, ST, LP, NT>>
+    where OP: OrderingProtocolMessage<D> {
+    /// Replace the network node used to forward messages to followers
+    /// (see [FollowerForwardHandle::replace_transport])
+    pub fn replace_send_node(&self, node: Arc<NT>) -> Result<()> {
+        self.replace_transport(NodeTransport::new(node))
+    }
+}
+
+impl<M, T> Clone for FollowerForwardHandle<M, T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+/// Why the follower handling loop has stopped
+enum StopReason {
+    /// We have been asked to shut down, and have to notify the given channel once we are done
+    Shutdown(ChannelSyncTx<()>),
+    /// The handles used to deliver events to us have all been dropped
+    Disconnected,
+}
+
+/// The sequences forwarded to and acknowledged by a follower
+#[derive(Default)]
+struct FollowerProgress {
+    /// The first sequence we have forwarded to the follower
+    first_forwarded: Option<SeqNo>,
+    /// The latest sequence we have forwarded to the follower
+    forwarded: Option<SeqNo>,
+    /// The latest sequence the follower has acknowledged
+    acked: Option<SeqNo>,
+}
+
+impl FollowerProgress {
+    /// How many sequences is the follower lagging behind what we have forwarded to it.
+    /// Until it acknowledges anything, it lags behind everything we have forwarded to it
+    /// (followers can join at any sequence, so we don't count from the first sequence ever)
+    fn lag(&self) -> u32 {
+        match (self.first_forwarded, self.forwarded, self.acked) {
+            (_, Some(forwarded), Some(acked)) => u32::from(forwarded).saturating_sub(u32::from(acked)),
+            (Some(first), Some(forwarded), None) => u32::from(forwarded).saturating_sub(u32::from(first)) + 1,
+            _ => 0,
+        }
+    }
+}
+
+/// The distinct forwarders observed by a follower, accumulated over the sequences it reported
+#[derive(Default)]
+struct ObservedCoverage {
+    sequences: u64,
+    forwarders: u64,
+}
+
+impl ObservedCoverage {
+    /// The average amount of distinct forwarders per sequence
+    fn average(&self) -> f64 {
+        if self.sequences == 0 {
+            0.0
+        } else {
+            self.forwarders as f64 / self.sequences as f64
+        }
+    }
+
+    /// An average coverage as it is reported to the metrics, in hundredths of a forwarder,
+    /// as the count metrics only take whole numbers
+    fn metric_value(average: f64) -> usize {
+        (average * 100.0).round() as usize
+    }
+}
+
+/// A forward which could not be delivered to a follower
+#[derive(Clone, Debug)]
+pub struct DeadLetter {
+    /// The follower the message was meant for
+    pub follower: NodeId,
+    /// The sequence number of the forwarded message
+    pub seq: SeqNo,
+    /// Why the message could not be delivered
+    pub reason: String,
+}
+
+/// The liveness information of a follower
+struct FollowerLiveness {
+    /// When we last heard from the follower
+    last_heard: Instant,
+    /// When the follower started being suspected of having gone silent
+    suspected_since: Option<Instant>,
+}
+
+impl FollowerLiveness {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_heard: now,
+            suspected_since: None,
+        }
+    }
+}
+
+/// Messages that are waiting to be delivered to a follower that batches its deliveries
+struct PendingBatch<M> {
+    messages: Vec<M>,
+    first_queued: Instant,
+}
+
+impl<M> PendingBatch<M> {
+    fn new(now: Instant) -> Self {
+        Self {
+            messages: Vec::new(),
+            first_queued: now,
+        }
+    }
+}
+
+/// Drives the follower handling manually, without a thread of its own.
+/// Each step runs a single iteration of the follower handling loop without waiting
+/// for events, so its effects can be asserted deterministically (combined with a
+/// [crate::clock::ManualClock] in the configuration).
+///
+/// Queries made through the [FollowerControlHandle] are only answered while stepping,
+/// so they have to be made from another thread.
+///
+/// ```ignore
+/// let (events, control, _forward, mut stepper) =
+///     FollowersFollowing::<FollowerEvent<D, OP, POP>, NodeTransport<D, OP, ST, LP, NT>>::init_stepped_follower_handling(id, config, &node, classify, Some(view));
+///
+/// control.register_follower(follower, FollowerBatchingPreference::default())?;
+///
+/// // Hand `events` to the ordering protocol, which delivers a pre prepare through it
+///
+/// // A single step registers the follower and forwards the pre prepare to it
+/// assert!(stepper.step());
+///
+/// // With every handle dropped, the follower handling stops
+/// drop((events, control));
+///
+/// assert!(!stepper.step());
+/// ```
+#[cfg(any(test, feature = "follower_step_mode"))]
+pub struct FollowerStepper<E: IntoFollowerInput, T> {
+    state: StepperState<E, T>,
+}
+
+#[cfg(any(test, feature = "follower_step_mode"))]
+enum StepperState<E: IntoFollowerInput, T> {
+    /// Driving the follower handling
+    Running(Box<FollowersFollowing<E, T>>),
+    /// Follower handling is disabled, so we only discard the events delivered to it
+    Discarding(ChannelSyncRx<E>),
+    Stopped,
+}
+
+#[cfg(any(test, feature = "follower_step_mode"))]
+impl<E, T> FollowerStepper<E, T> where E: IntoFollowerInput, T: FollowerTransport<E::Message> {
+    /// Run a single iteration of the follower handling loop.
+    /// Returns false once the follower handling has been shut down
+    pub fn step(&mut self) -> bool {
+        let running = match &mut self.state {
+            StepperState::Running(follower_handling) => follower_handling.step(Duration::ZERO),
+            StepperState::Discarding(rx) => Self::discard_events(rx),
+            StepperState::Stopped => return false,
+        };
+
+        if !running {
+            self.state = StepperState::Stopped;
+        }
+
+        running
+    }
+
+    /// Discard all of the events that are waiting.
+    /// Returns false once all of the event handles have been dropped
+    fn discard_events(rx: &ChannelSyncRx<E>) -> bool {
+        loop {
+            match rx.try_recv() {
+                Ok(_) => {}
+                Err(TryRecvError::ChannelDc) => return false,
+                Err(_) => return true,
+            }
+        }
+    }
+}
 
 /// Store information of the current followers of the quorum
 /// This information will be used to calculate which replicas have to send the
@@ -22,212 +833,2070 @@ use atlas_core::state_transfer::networking::serialize::StateTransferMessage;
 /// This routing is only relevant to the Preprepare requests, all other requests
 /// Can be broadcast from each replica as they are very small and therefore
 /// don't have any effects on performance
-struct FollowersFollowing<D, OP: OrderingProtocolMessage<D>, POP: PermissionedOrderingProtocolMessage, NT> {
+pub struct FollowersFollowing<E: IntoFollowerInput, T> {
     own_id: NodeId,
+    config: FollowerHandlingConfig,
+    /// Tells us the consensus phase of the messages of the ordering protocol,
+    /// which are otherwise opaque to us
+    classify_consensus: ConsensusClassifier<E::Message>,
+    /// The registered followers, kept sorted so all replicas arrange them in the same way
     followers: Vec<NodeId>,
-    send_node: Arc<NT>,
-    rx: ChannelSyncRx<FollowerChannelMsg<D, OP, POP>>,
+    /// The batching preferences of the followers which requested batched deliveries
+    batching_preferences: BTreeMap<NodeId, FollowerBatchingPreference>,
+    /// The messages that are waiting to be delivered to batching followers
+    pending_batches: BTreeMap<NodeId, PendingBatch<E::Message>>,
+    /// The prepares and commits that are being held until their sequence commits locally
+    /// (only used when forwarding only after commit)
+    held_until_commit: BTreeMap<SeqNo, Vec<Arc<ReadOnly<E::Message>>>>,
+    /// The highest sequence we know to have been committed locally. Our votes for it (and
+    /// previous sequences) which only reach us after the commit notice are not held
+    highest_committed: Option<SeqNo>,
+    /// The liveness of each of the registered followers
+    liveness: BTreeMap<NodeId, FollowerLiveness>,
+    /// The highest consensus sequence we have forwarded to any follower
+    highest_forwarded: Option<SeqNo>,
+    /// The progress of each of the followers we have forwarded messages to
+    progress: BTreeMap<NodeId, FollowerProgress>,
+    /// The coverage each follower has reported observing
+    observed_coverage: BTreeMap<NodeId, ObservedCoverage>,
+    /// The followers that are quarantined, along with when their quarantine ends
+    /// (`None` if it lasts until it is explicitly lifted)
+    quarantined: BTreeMap<NodeId, Option<Instant>>,
+    /// The followers we have failed to deliver messages to, along with how many
+    /// consecutive failures we have had with each of them
+    failing_followers: BTreeMap<NodeId, usize>,
+    /// The most recent forwards we were unable to deliver
+    dead_letters: VecDeque<DeadLetter>,
+    /// The sequence numbers whose routing we have been asked to trace
+    traced_sequences: BTreeSet<SeqNo>,
+    /// The routing decisions recorded for the traced sequences
+    routing_traces: BTreeMap<SeqNo, RoutingTrace>,
+    /// When we last processed an event
+    last_activity: Option<Instant>,
+    /// The digest of the routing table of each of the most recent views
+    routing_digests: BTreeMap<SeqNo, Digest>,
+    /// The region each follower has been tagged with
+    regions: BTreeMap<NodeId, String>,
+    /// The strategy used to select the followers we forward pre prepares to
+    target_strategy: Box<dyn FollowerTargetStrategy>,
+    /// The latest view we have seen from the ordering protocol
+    current_view: Option<E::View>,
+    /// How many of the events we have drained from the channel into
+    /// the backlog are still waiting to be handled
+    backlog_pending: usize,
+    transport: T,
+    rx: ChannelSyncRx<E>,
+    /// The administrative messages, until all of the control handles have been dropped
+    control_rx: Option<ChannelSyncRx<FollowerControlMsg>>,
+    forward_rx: ChannelSyncRx<ForwardControlMsg<E::Message, T>>,
+}
+
+impl<D, OP, POP, ST, LP, NT> FollowersFollowing<FollowerEvent<D, OP, POP>, NodeTransport<D, OP, ST, LP, NT>> where
+    D: ApplicationData + 'static,
+    OP: OrderingProtocolMessage<D> + 'static,
+    POP: PermissionedOrderingProtocolMessage + 'static,
+    ST: StateTransferMessage + 'static,
+    LP: LogTransferMessage<D, OP> + 'static,
+    NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> + Send + Sync + 'static {
+    /// Starts the follower handling thread and returns cloneable handles that
+    /// can be used to deliver messages to it.
+    ///
+    /// The consensus messages delivered by the ordering protocol are routed according
+    /// to the phase `classify_consensus` reports for them.
+    ///
+    /// The thread can be seeded with the last view that is known (for example, from
+    /// persistence) so it can route messages before it receives a view from the ordering protocol
+    pub fn init_follower_handling(id: NodeId, config: FollowerHandlingConfig, node: &Arc<NT>,
+                                  classify_consensus: fn(&OP::ProtocolMessage) -> ConsensusPhase,
+                                  last_known_view: Option<POP::ViewInfo>)
+                                  -> (FollowerHandle<D, OP, POP>, FollowerControlHandle, FollowerForwardHandle<ProtocolForward<D, OP>, NodeTransport<D, OP, ST, LP, NT>>) {
+        let (tx, control, forward) = Self::init_with_transport(id, config, NodeTransport::new(Arc::clone(node)),
+                                                               Self::protocol_classifier(classify_consensus), last_known_view);
+
+        (FollowerHandle::new(tx), control, forward)
+    }
+
+    /// Set up the follower handling without starting its thread. Instead, the returned
+    /// stepper has to be used to drive the follower handling, one iteration at a time.
+    /// Meant to test the follower handling deterministically
+    #[cfg(feature = "follower_step_mode")]
+    pub fn init_stepped_follower_handling(id: NodeId, config: FollowerHandlingConfig, node: &Arc<NT>,
+                                          classify_consensus: fn(&OP::ProtocolMessage) -> ConsensusPhase,
+                                          last_known_view: Option<POP::ViewInfo>)
+                                          -> (FollowerHandle<D, OP, POP>, FollowerControlHandle,
+                                              FollowerForwardHandle<ProtocolForward<D, OP>, NodeTransport<D, OP, ST, LP, NT>>, FollowerStepper<FollowerEvent<D, OP, POP>, NodeTransport<D, OP, ST, LP, NT>>) {
+        let (tx, control, forward, stepper) = Self::init_stepped_with_transport(id, config, NodeTransport::new(Arc::clone(node)),
+                                                                               Self::protocol_classifier(classify_consensus), last_known_view);
+
+        (FollowerHandle::new(tx), control, forward, stepper)
+    }
+
+    /// Classify the stored messages by the phase of the protocol message they carry
+    fn protocol_classifier(classify_consensus: fn(&OP::ProtocolMessage) -> ConsensusPhase) -> ConsensusClassifier<ProtocolForward<D, OP>> {
+        Box::new(move |message: &ProtocolForward<D, OP>| classify_consensus(message.message().payload()))
+    }
+}
+
+impl<E, T> FollowersFollowing<E, T> where E: IntoFollowerInput, T: FollowerTransport<E::Message> {
+    /// Starts the follower handling thread, forwarding through the given transport,
+    /// and returns cloneable handles that can be used to deliver messages to it
+    pub fn init_with_transport(id: NodeId, config: FollowerHandlingConfig, transport: T,
+                               classify_consensus: ConsensusClassifier<E::Message>,
+                               last_known_view: Option<E::View>)
+                               -> (ChannelSyncTx<E>, FollowerControlHandle, FollowerForwardHandle<E::Message, T>) {
+        let (tx, rx) = channel::new_bounded_sync(FOLLOWER_CHANNEL_SIZE);
+
+        if !config.enabled {
+            info!("{:?} // Follower handling is disabled, discarding the events delivered to it", id);
+
+            //The events are still taken from the channel, so the ordering protocol never blocks
+            //on it or sees its deliveries fail, while the other handles don't deliver anything at all
+            Self::start_discarding_thread(id, rx);
+
+            return (tx, FollowerControlHandle::disabled(), FollowerForwardHandle::disabled());
+        }
+
+        let (control_tx, control_rx) = channel::new_bounded_sync(FOLLOWER_CONTROL_CHANNEL_SIZE);
+        let (forward_tx, forward_rx) = channel::new_bounded_sync(FOLLOWER_CONTROL_CHANNEL_SIZE);
+
+        let follower_handling = Self::new(id, config, transport, classify_consensus, rx, control_rx, forward_rx, last_known_view);
+
+        follower_handling.start_thread();
+
+        (tx, FollowerControlHandle::new(control_tx), FollowerForwardHandle::new(forward_tx))
+    }
+
+    /// Set up the follower handling, forwarding through the given transport, without
+    /// starting its thread (see [FollowerStepper])
+    #[cfg(any(test, feature = "follower_step_mode"))]
+    pub fn init_stepped_with_transport(id: NodeId, config: FollowerHandlingConfig, transport: T,
+                                       classify_consensus: ConsensusClassifier<E::Message>,
+                                       last_known_view: Option<E::View>)
+                                       -> (ChannelSyncTx<E>, FollowerControlHandle, FollowerForwardHandle<E::Message, T>, FollowerStepper<E, T>) {
+        let (tx, rx) = channel::new_bounded_sync(FOLLOWER_CHANNEL_SIZE);
+
+        if !config.enabled {
+            info!("{:?} // Follower handling is disabled, the stepper will discard the events delivered to it", id);
+
+            return (tx, FollowerControlHandle::disabled(), FollowerForwardHandle::disabled(),
+                    FollowerStepper { state: StepperState::Discarding(rx) });
+        }
+
+        let (control_tx, control_rx) = channel::new_bounded_sync(FOLLOWER_CONTROL_CHANNEL_SIZE);
+        let (forward_tx, forward_rx) = channel::new_bounded_sync(FOLLOWER_CONTROL_CHANNEL_SIZE);
+
+        let follower_handling = Self::new(id, config, transport, classify_consensus, rx, control_rx, forward_rx, last_known_view);
+
+        (tx, FollowerControlHandle::new(control_tx), FollowerForwardHandle::new(forward_tx),
+         FollowerStepper { state: StepperState::Running(Box::new(follower_handling)) })
+    }
+
+    fn new(id: NodeId, config: FollowerHandlingConfig, transport: T,
+           classify_consensus: ConsensusClassifier<E::Message>,
+           rx: ChannelSyncRx<E>,
+           control_rx: ChannelSyncRx<FollowerControlMsg>,
+           forward_rx: ChannelSyncRx<ForwardControlMsg<E::Message, T>>,
+           last_known_view: Option<E::View>) -> Self {
+        let mut follower_handling = Self {
+            own_id: id,
+            config,
+            classify_consensus,
+            followers: Vec::new(),
+            batching_preferences: BTreeMap::new(),
+            pending_batches: BTreeMap::new(),
+            held_until_commit: BTreeMap::new(),
+            highest_committed: None,
+            failing_followers: BTreeMap::new(),
+            liveness: BTreeMap::new(),
+            highest_forwarded: None,
+            progress: BTreeMap::new(),
+            observed_coverage: BTreeMap::new(),
+            quarantined: BTreeMap::new(),
+            dead_letters: VecDeque::new(),
+            traced_sequences: BTreeSet::new(),
+            routing_traces: BTreeMap::new(),
+            last_activity: None,
+            routing_digests: BTreeMap::new(),
+            regions: BTreeMap::new(),
+            target_strategy: Box::new(FairSplitStrategy),
+            current_view: None,
+            backlog_pending: 0,
+            transport,
+            rx,
+            control_rx: Some(control_rx),
+            forward_rx,
+        };
+
+        if let Some(view) = last_known_view {
+            follower_handling.update_view(&view);
+        }
+
+        follower_handling
+    }
+
+    /// Start a thread which discards the events delivered while follower handling is disabled,
+    /// until all of the event handles have been dropped
+    fn start_discarding_thread(id: NodeId, rx: ChannelSyncRx<E>) {
+        std::thread::Builder::new()
+            .name(format!(
+                "Follower Event Discarding Thread for node {:?}",
+                id
+            ))
+            .spawn(move || {
+                while rx.recv().is_ok() {}
+            })
+            .expect("Failed to launch follower event discarding thread!");
+    }
+
+    fn start_thread(self) {
+        std::thread::Builder::new()
+            .name(format!(
+                "Follower Handling Thread for node {:?}",
+                self.own_id
+            ))
+            .spawn(move || {
+                self.run();
+            })
+            .expect("Failed to launch follower handling thread!");
+    }
+
+    fn run(mut self) {
+        while self.step(FOLLOWER_THREAD_WAIT_TIME) {}
+    }
+
+    /// Run a single iteration of the follower handling loop, waiting at most `wait` for
+    /// new events. Returns false once we have been shut down
+    fn step(&mut self, wait: Duration) -> bool {
+        if let Some(reason) = self.receive_control_messages() {
+            self.shutdown(reason);
+
+            return false;
+        }
+
+        self.receive_forward_control();
+
+        self.release_expired_quarantines();
+        self.sweep_silent_followers();
+
+        if self.is_over_clone_cap() {
+            //Don't clone anything else until the retained messages are delivered,
+            //so the backpressure propagates to whoever is producing the events
+            std::thread::sleep(wait);
+        } else {
+            match self.rx.recv_timeout(wait) {
+                Ok(message) => {
+                    let message = message.into_input();
+
+                    if self.rx.is_empty() {
+                        self.handle_follower_event(message);
+                    } else {
+                        self.handle_backlog(message);
+                    }
+                }
+                Err(TryRecvError::ChannelDc) => {
+                    info!("{:?} // All of the follower handles have been dropped, stopping follower handling", self.own_id);
+
+                    self.shutdown(StopReason::Disconnected);
+
+                    return false;
+                }
+                Err(_) => {}
+            }
+        }
+
+        self.flush_expired_batches();
+
+        true
+    }
+
+    /// Process the pending forward control messages. Since these are handled between
+    /// events, the transport is never replaced in the middle of a forward
+    fn receive_forward_control(&mut self) {
+        while let Ok(message) = self.forward_rx.try_recv() {
+            match message {
+                ForwardControlMsg::Directed(directed) => {
+                    self.directed_forward(directed);
+                }
+                ForwardControlMsg::ReplaceTransport(transport) => {
+                    info!("{:?} // Replacing the transport used to forward messages to followers", self.own_id);
+
+                    self.transport = transport;
+                }
+            }
+        }
+    }
+
+    /// Forward a message directed to an explicit subset of the followers
+    fn directed_forward(&mut self, directed: DirectedForward<E::Message>) {
+        let DirectedForward { mut targets, message, response } = directed;
+
+        self.last_activity = Some(self.config.clock.now());
+
+        targets.sort();
+        targets.dedup();
+
+        let unregistered: Vec<NodeId> = targets.iter()
+            .filter(|target| self.followers.binary_search(target).is_err())
+            .copied()
+            .collect();
+
+        if unregistered.is_empty() {
+            //Quarantined followers don't get anything forwarded, even when explicitly targeted
+            let (quarantined, targets): (Vec<NodeId>, Vec<NodeId>) = targets.into_iter()
+                .partition(|target| self.quarantined.contains_key(target));
+
+            if !quarantined.is_empty() {
+                debug!("{:?} // Not forwarding directed message to quarantined followers {:?}", self.own_id, quarantined);
+            }
+
+            self.forward_to_followers(&message, targets.into_iter());
+        } else {
+            warn!("{:?} // Refusing directed forward, as {:?} are not registered followers", self.own_id, unregistered);
+        }
+
+        if let Err(err) = response.send(unregistered) {
+            warn!("{:?} // Failed to respond to directed forward: {:?}", self.own_id, err);
+        }
+    }
+
+    /// Are the cloned messages we are retaining over the configured cap?
+    fn is_over_clone_cap(&self) -> bool {
+        let max_bytes = match self.config.max_retained_clone_bytes {
+            Some(max_bytes) => max_bytes,
+            None => return false,
+        };
+
+        let retained = self.retained_clone_bytes();
+
+        if retained > max_bytes {
+            debug!("{:?} // Retaining {} bytes of cloned messages (cap is {}), not taking new events",
+                self.own_id, retained, max_bytes);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The amount of bytes of the cloned messages that are waiting to be delivered.
+    ///
+    /// Messages held until commit are not counted: they are shared with the consensus thread
+    /// instead of cloned, and are only released by a commit which may need the ordering
+    /// protocol to make progress, which it can't do while we are not taking its events
+    fn retained_clone_bytes(&self) -> usize {
+        self.pending_batches.values()
+            .flat_map(|batch| batch.messages.iter())
+            .map(|message| message.payload_len())
+            .sum()
+    }
+
+    /// We have fallen behind and have a backlog of events. Drain it and forward the
+    /// consensus events oldest view and sequence first, so followers catch up in order
+    /// instead of seeing newer messages while older ones are still queued.
+    /// Pre prepares are latency sensitive, so they go ahead of our own votes.
+    ///
+    /// Sync events are never reordered, so consensus events are only sorted among
+    /// the ones that were received between two sync events
+    fn handle_backlog(&mut self, first: FollowerInput<E::View, E::Message>) {
+        let mut backlog = Vec::with_capacity(self.rx.len() + 1);
+
+        backlog.push(first);
+
+        while backlog.len() < FOLLOWER_BACKLOG_DRAIN {
+            match self.rx.try_recv() {
+                Ok(message) => backlog.push(message.into_input()),
+                Err(_) => break
+            }
+        }
+
+        debug!("{:?} // Forwarding backlog of {} events in sequence order", self.own_id, backlog.len());
+
+        self.backlog_pending = backlog.len();
+
+        let mut consensus_run = Vec::new();
+
+        for message in backlog {
+            match message {
+                FollowerInput::Consensus(view, consensus_msg) => {
+                    consensus_run.push((view, consensus_msg));
+                }
+                sync_event => {
+                    self.handle_consensus_run(&mut consensus_run);
+
+                    self.backlog_pending -= 1;
+
+                    self.handle_follower_event(sync_event);
+                }
+            }
+        }
+
+        self.handle_consensus_run(&mut consensus_run);
+    }
+
+    /// Handle a run of consecutive consensus events of the backlog, ordered by view,
+    /// priority and sequence. Events that are otherwise equal keep the order in which they were received
+    fn handle_consensus_run(&mut self, run: &mut Vec<(E::View, Arc<ReadOnly<E::Message>>)>) {
+        run.sort_by_key(|(view, consensus_msg)| {
+            (view.sequence_number(), self.consensus_priority(consensus_msg), consensus_msg.seq())
+        });
+
+        for (view, consensus_msg) in run.drain(..) {
+            self.backlog_pending -= 1;
+
+            self.handle_follower_event(FollowerInput::Consensus(view, consensus_msg));
+        }
+    }
+
+    /// The forwarding priority of a consensus message (lower goes first), according to
+    /// the phase the ordering protocol classifies it in. Pre prepares go first, followed
+    /// by the votes in the order they are cast
+    fn consensus_priority(&self, consensus_msg: &Arc<ReadOnly<E::Message>>) -> u8 {
+        match (self.classify_consensus)(&**consensus_msg) {
+            ConsensusPhase::PrePrepare => 0,
+            ConsensusPhase::Prepare => 1,
+            ConsensusPhase::Commit => 2,
+        }
+    }
+
+    /// The sequence number of the message carried by an event
+    fn event_sequence(message: &FollowerInput<E::View, E::Message>) -> SeqNo {
+        match message {
+            FollowerInput::Consensus(_, consensus_msg) => {
+                consensus_msg.seq()
+            }
+            FollowerInput::Sync(view_change_msg) => {
+                view_change_msg.seq()
+            }
+        }
+    }
+
+    /// Handle an event produced by the ordering protocol, containing any panic
+    /// to this event if we are configured to do so
+    fn handle_follower_event(&mut self, message: FollowerInput<E::View, E::Message>) {
+        self.last_activity = Some(self.config.clock.now());
+
+        //Keep track of the view before handing the event to its handler, so the
+        //cached view is up to date even if the handler fails
+        if let FollowerInput::Consensus(view, _) = &message {
+            self.update_view(view);
+        }
+
+        if !self.config.isolate_event_panics {
+            self.dispatch_follower_event(message);
+
+            return;
+        }
+
+        let seq = Self::event_sequence(&message);
+
+        //The state touched by a handler that panicked may be left half updated, but that
+        //only affects forwarding to followers, which is best effort anyway
+        if let Err(panic) = catch_unwind(AssertUnwindSafe(|| self.dispatch_follower_event(message))) {
+            let reason = panic.downcast_ref::<&str>().map(|reason| reason.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+
+            error!("{:?} // Handling the follower event for sequence {:?} panicked ({}), dropping it",
+                self.own_id, seq, reason);
+
+            metric_increment(FOLLOWER_EVENT_PANICS_ID, Some(1));
+        }
+    }
+
+    /// Dispatch an event produced by the ordering protocol to its handler
+    fn dispatch_follower_event(&mut self, message: FollowerInput<E::View, E::Message>) {
+        match message {
+            FollowerInput::Consensus(view, consensus_msg) => {
+                match (self.classify_consensus)(&**consensus_msg) {
+                    ConsensusPhase::PrePrepare => self.handle_preprepare_msg_rcvd(&view, consensus_msg),
+                    ConsensusPhase::Prepare => self.handle_prepare_msg(consensus_msg),
+                    ConsensusPhase::Commit => self.handle_commit_msg(consensus_msg),
+                }
+            }
+            FollowerInput::Sync(view_change_msg) => {
+                self.handle_sync_msg(view_change_msg)
+            }
+        }
+    }
+
+    /// Update the latest view we know of, checking the new view when it changes.
+    /// Events of older views can still be handled after a newer view is known,
+    /// so the view only ever moves forward
+    fn update_view(&mut self, view: &E::View) {
+        let newer = self.current_view.as_ref()
+            .map_or(true, |current| view.sequence_number() > current.sequence_number());
+
+        if !newer {
+            return;
+        }
+
+        self.check_view_coverage(view);
+
+        if !Self::is_member(view, self.own_id) {
+            warn!("{:?} // We are not a member of view {:?}, spectating it without forwarding to followers",
+                self.own_id, view.sequence_number());
+        }
+
+        self.current_view = Some(view.clone());
+
+        self.record_routing_digest();
+    }
+
+    /// Is the given replica a member of the given view?
+    fn is_member(view: &E::View, replica: NodeId) -> bool {
+        view.quorum_members().contains(&replica)
+    }
+
+    /// Record the digest of the routing table of the latest view.
+    /// Quarantines are a local decision, so the digest covers all registered followers
+    fn record_routing_digest(&mut self) {
+        let view = match &self.current_view {
+            Some(view) => view,
+            None => return,
+        };
+
+        let routing_table = RoutingTable::compute(view, &self.followers, self.config.leader_forwards_preprepares);
+
+        self.routing_digests.insert(routing_table.view(), routing_table.digest());
+
+        while self.routing_digests.len() > ROUTING_DIGEST_HISTORY {
+            self.routing_digests.pop_first();
+        }
+    }
+
+    /// Check whether the view has enough replicas to give each follower f + 1
+    /// distinct forwarders. If it doesn't, followers can't be fully protected
+    /// against message dropping, and the routing degrades to using all available replicas
+    fn check_view_coverage(&self, view: &E::View) {
+        let available = routing::available_forwarders(view, self.config.leader_forwards_preprepares);
+
+        if view.f() + 1 > available {
+            if self.config.warn_on_insufficient_forwarders {
+                warn!("{:?} // View {:?} only has {} replicas available to forward, but f + 1 = {} are required to protect followers",
+                    self.own_id, view.sequence_number(), available, view.f() + 1);
+            }
+
+            metric_increment(FOLLOWER_INSUFFICIENT_FORWARDERS_ID, Some(1));
+        }
+    }
+
+    /// Recompute the routing of pre prepares for the latest view and the active followers
+    fn recompute_assignments(&self) -> Option<RoutingTable> {
+        let view = self.current_view.as_ref()?;
+
+        let routing_table = RoutingTable::compute(view, &self.active_followers(), self.config.leader_forwards_preprepares);
+
+        info!("{:?} // Recomputed follower assignments for view {:?}: {:?}",
+            self.own_id, routing_table.view(), routing_table.assignments());
+
+        Some(routing_table)
+    }
+
+    /// Process all of the pending administrative messages.
+    /// Returns why we have to stop, if we have been asked to shut down.
+    ///
+    /// Once all of the control handles have been dropped, we stop polling for administrative
+    /// messages but keep forwarding events for as long as the ordering protocol delivers them
+    fn receive_control_messages(&mut self) -> Option<StopReason> {
+        loop {
+            let control_rx = self.control_rx.as_ref()?;
+
+            let message = match control_rx.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::ChannelDc) => {
+                    info!("{:?} // All of the follower control handles have been dropped, no longer taking administrative messages", self.own_id);
+
+                    self.control_rx = None;
+
+                    return None;
+                }
+                Err(_) => return None,
+            };
+
+            match message {
+                FollowerControlMsg::RegisterFollower(follower, preference) => {
+                    self.register_follower(follower, preference);
+                }
+                FollowerControlMsg::RemoveFollower(follower) => {
+                    self.remove_follower(follower);
+                }
+                FollowerControlMsg::FollowerHeartbeat(follower) => {
+                    self.follower_heartbeat(follower);
+                }
+                FollowerControlMsg::FollowerAcked(follower, seq) => {
+                    self.follower_acked(follower, seq);
+                }
+                FollowerControlMsg::ForwardersObserved(follower, seq, forwarders) => {
+                    self.forwarders_observed(follower, seq, forwarders);
+                }
+                FollowerControlMsg::ObservedCoverage(response) => {
+                    let coverage = self.observed_coverage.iter()
+                        .map(|(follower, coverage)| (*follower, coverage.average()))
+                        .collect();
+
+                    if let Err(err) = response.send(coverage) {
+                        warn!("{:?} // Failed to respond to observed coverage query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::TagFollowerRegion(follower, region) => {
+                    self.tag_follower_region(follower, region);
+                }
+                FollowerControlMsg::UpdateFollowerControls(follower, controls) => {
+                    self.update_follower_controls(follower, controls);
+                }
+                FollowerControlMsg::QuarantineFollower(follower, duration) => {
+                    self.quarantine_follower(follower, duration);
+                }
+                FollowerControlMsg::SequenceCommitted(seq) => {
+                    self.release_held_messages(seq);
+                }
+                FollowerControlMsg::IsFollower(node, response) => {
+                    let is_follower = self.followers.binary_search(&node).is_ok();
+
+                    if let Err(err) = response.send(is_follower) {
+                        warn!("{:?} // Failed to respond to follower membership query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::FollowerLag(response) => {
+                    let lag = self.progress.iter()
+                        .map(|(follower, progress)| (*follower, progress.lag()))
+                        .collect();
+
+                    if let Err(err) = response.send(lag) {
+                        warn!("{:?} // Failed to respond to follower lag query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::HighestForwarded(response) => {
+                    if let Err(err) = response.send(self.highest_forwarded) {
+                        warn!("{:?} // Failed to respond to highest forwarded query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::DeadLetters(response) => {
+                    let dead_letters = self.dead_letters.iter().cloned().collect();
+
+                    if let Err(err) = response.send(dead_letters) {
+                        warn!("{:?} // Failed to respond to dead letter query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::FailingFollowers(response) => {
+                    if let Err(err) = response.send(self.failing_followers.clone()) {
+                        warn!("{:?} // Failed to respond to failing followers query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::TraceRouting(seq) => {
+                    self.traced_sequences.insert(seq);
+                }
+                FollowerControlMsg::TakeRoutingTrace(seq, response) => {
+                    if let Err(err) = response.send(self.routing_traces.remove(&seq)) {
+                        warn!("{:?} // Failed to respond to routing trace query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::RecomputeAssignments(response) => {
+                    let routing_table = self.recompute_assignments();
+
+                    if let Err(err) = response.send(routing_table) {
+                        warn!("{:?} // Failed to respond to assignment recomputation: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::PendingEvents(response) => {
+                    if let Err(err) = response.send(self.rx.len()) {
+                        warn!("{:?} // Failed to respond to pending events query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::IsActive(within, response) => {
+                    let active = self.last_activity
+                        .map_or(false, |last_activity| self.config.clock.elapsed_since(last_activity) <= within);
+
+                    if let Err(err) = response.send(active) {
+                        warn!("{:?} // Failed to respond to activity query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::RoutingDigests(response) => {
+                    if let Err(err) = response.send(self.routing_digests.clone()) {
+                        warn!("{:?} // Failed to respond to routing digest query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::QuorumParams(response) => {
+                    let params = self.current_view.as_ref().map(|view| (view.n(), view.f()));
+
+                    if let Err(err) = response.send(params) {
+                        warn!("{:?} // Failed to respond to quorum params query: {:?}", self.own_id, err);
+                    }
+                }
+                FollowerControlMsg::SetTargetStrategy(strategy) => {
+                    info!("{:?} // Replacing the follower target strategy", self.own_id);
+
+                    self.target_strategy = strategy;
+                }
+                FollowerControlMsg::Shutdown(notify) => {
+                    return Some(StopReason::Shutdown(notify));
+                }
+            }
+        }
+    }
+
+    /// Shut down the follower handling. Events that were already handed to us are
+    /// still forwarded, followed by all of the pending batches
+    fn shutdown(&mut self, reason: StopReason) {
+        info!("{:?} // Shutting down follower handling, draining pending events", self.own_id);
+
+        while let Ok(message) = self.rx.try_recv() {
+            self.handle_follower_event(message.into_input());
+        }
+
+        let pending: Vec<NodeId> = self.pending_batches.keys().copied().collect();
+
+        for follower in pending {
+            self.flush_batch(follower);
+        }
+
+        if let StopReason::Shutdown(notify) = reason {
+            if let Err(err) = notify.send(()) {
+                warn!("{:?} // Failed to notify of follower handling shutdown: {:?}", self.own_id, err);
+            }
+        }
+    }
+
+    /// Register a follower, keeping the follower list sorted
+    fn register_follower(&mut self, follower: NodeId, preference: FollowerBatchingPreference) {
+        match self.followers.binary_search(&follower) {
+            Ok(_) => {
+                warn!("{:?} // Tried to double register follower {:?}, updating its preferences", self.own_id, follower);
+            }
+            Err(index) => {
+                info!("{:?} // Registered follower {:?} with batching preference {:?}", self.own_id, follower, preference);
+
+                self.followers.insert(index, follower);
+            }
+        }
+
+        self.liveness.insert(follower, FollowerLiveness::new(self.config.clock.now()));
+
+        if preference.is_batching() {
+            self.batching_preferences.insert(follower, preference);
+        } else {
+            self.batching_preferences.remove(&follower);
+        }
+
+        self.record_routing_digest();
+    }
+
+    /// Remove a follower, discarding any messages that were pending for it
+    fn remove_follower(&mut self, follower: NodeId) {
+        if let Ok(index) = self.followers.binary_search(&follower) {
+            self.followers.remove(index);
+
+            info!("{:?} // Removed follower {:?}", self.own_id, follower);
+        } else {
+            warn!("{:?} // Tried to remove follower {:?} which is not registered", self.own_id, follower);
+        }
+
+        self.batching_preferences.remove(&follower);
+        self.pending_batches.remove(&follower);
+        self.failing_followers.remove(&follower);
+        self.quarantined.remove(&follower);
+        self.liveness.remove(&follower);
+        self.progress.remove(&follower);
+        self.observed_coverage.remove(&follower);
+        self.regions.remove(&follower);
+
+        self.record_routing_digest();
+    }
+
+    /// A follower has acknowledged everything up to the given sequence
+    fn follower_acked(&mut self, follower: NodeId, seq: SeqNo) {
+        if self.followers.binary_search(&follower).is_err() {
+            return;
+        }
+
+        let progress = self.progress.entry(follower).or_default();
+
+        if progress.acked.map_or(true, |acked| seq > acked) {
+            progress.acked = Some(seq);
+        }
+
+        self.report_max_lag();
+    }
+
+    /// Record that we have forwarded the given consensus sequence to the given followers.
+    /// Sync messages carry view numbers instead, so they are not recorded
+    fn record_forwarded(&mut self, seq: SeqNo, followers: &[NodeId]) {
+        if !followers.is_empty() && self.highest_forwarded.map_or(true, |highest| seq > highest) {
+            self.highest_forwarded = Some(seq);
+        }
+
+        for follower in followers {
+            let progress = self.progress.entry(*follower).or_default();
+
+            if progress.first_forwarded.is_none() {
+                progress.first_forwarded = Some(seq);
+            }
+
+            if progress.forwarded.map_or(true, |forwarded| seq > forwarded) {
+                progress.forwarded = Some(seq);
+            }
+        }
+
+        self.report_max_lag();
+    }
+
+    /// A follower has reported the replicas it received the pre prepare of a sequence from
+    fn forwarders_observed(&mut self, follower: NodeId, seq: SeqNo, mut forwarders: Vec<NodeId>) {
+        if self.followers.binary_search(&follower).is_err() {
+            return;
+        }
+
+        forwarders.sort();
+        forwarders.dedup();
+
+        if let Some(view) = &self.current_view {
+            if forwarders.len() < view.f() + 1 {
+                warn!("{:?} // Follower {:?} only received sequence {:?} from {:?}, which is less than the f + 1 = {} intended forwarders",
+                    self.own_id, follower, seq, forwarders, view.f() + 1);
+            }
+        }
+
+        let coverage = self.observed_coverage.entry(follower).or_default();
+
+        coverage.sequences += 1;
+        coverage.forwarders += forwarders.len() as u64;
+
+        let min_coverage = self.observed_coverage.values()
+            .map(ObservedCoverage::average)
+            .fold(f64::INFINITY, f64::min);
+
+        metric_store_count(FOLLOWER_MIN_OBSERVED_COVERAGE_ID, ObservedCoverage::metric_value(min_coverage));
+    }
+
+    /// Report the largest lag of any of our followers
+    fn report_max_lag(&self) {
+        let max_lag = self.progress.values()
+            .map(FollowerProgress::lag)
+            .max()
+            .unwrap_or(0);
+
+        metric_store_count(FOLLOWER_MAX_SEQUENCE_LAG_ID, max_lag as usize);
+    }
+
+    /// We have heard from a follower, clearing any suspicion of it having gone silent
+    fn follower_heartbeat(&mut self, follower: NodeId) {
+        if let Some(liveness) = self.liveness.get_mut(&follower) {
+            liveness.last_heard = self.config.clock.now();
+
+            if liveness.suspected_since.take().is_some() {
+                info!("{:?} // Heard from suspected follower {:?}, clearing suspicion", self.own_id, follower);
+            }
+        }
+    }
+
+    /// Check the liveness of the followers. Followers which have been silent for longer than
+    /// the liveness interval become suspected, and are only evicted if they remain silent
+    /// for the grace period after that, so transient blips have time to recover
+    fn sweep_silent_followers(&mut self) {
+        let interval = match self.config.follower_liveness_interval {
+            Some(interval) => interval,
+            None => return
+        };
+
+        let now = self.config.clock.now();
+        let grace = self.config.follower_eviction_grace;
+
+        let mut to_evict = Vec::new();
+
+        for (follower, liveness) in self.liveness.iter_mut() {
+            if now.saturating_duration_since(liveness.last_heard) < interval {
+                continue;
+            }
+
+            match liveness.suspected_since {
+                None => {
+                    warn!("{:?} // Follower {:?} has been silent for {:?}, suspecting it", self.own_id, follower, interval);
+
+                    liveness.suspected_since = Some(now);
+                }
+                Some(suspected_since) if now.saturating_duration_since(suspected_since) >= grace => {
+                    to_evict.push(*follower);
+                }
+                Some(_) => {}
+            }
+        }
+
+        for follower in to_evict {
+            warn!("{:?} // Follower {:?} remained silent after being suspected, evicting it", self.own_id, follower);
+
+            self.remove_follower(follower);
+        }
+    }
+
+    /// Tag a registered follower with its region
+    fn tag_follower_region(&mut self, follower: NodeId, region: String) {
+        if self.followers.binary_search(&follower).is_err() {
+            warn!("{:?} // Tried to tag follower {:?} which is not registered with region {}", self.own_id, follower, region);
+
+            return;
+        }
+
+        debug!("{:?} // Tagged follower {:?} with region {}", self.own_id, follower, region);
+
+        self.regions.insert(follower, region);
+    }
+
+    /// Replace all of the controls of a registered follower. Since control messages are
+    /// handled between events, the new controls all take effect on the same forward
+    fn update_follower_controls(&mut self, follower: NodeId, controls: FollowerControls) {
+        if self.followers.binary_search(&follower).is_err() {
+            warn!("{:?} // Tried to update the controls of follower {:?} which is not registered", self.own_id, follower);
+
+            return;
+        }
+
+        debug!("{:?} // Updating the controls of follower {:?} to {:?}", self.own_id, follower, controls);
+
+        let FollowerControls { batching, quarantine, region } = controls;
+
+        if batching.is_batching() {
+            self.batching_preferences.insert(follower, batching);
+        } else {
+            self.batching_preferences.remove(&follower);
+
+            //Whatever was being batched is delivered now, as the follower no longer batches
+            self.flush_batch(follower);
+        }
+
+        match quarantine {
+            Some(duration) => self.quarantine_follower(follower, duration),
+            None => {
+                self.quarantined.remove(&follower);
+            }
+        }
+
+        match region {
+            Some(region) => {
+                self.regions.insert(follower, region);
+            }
+            None => {
+                self.regions.remove(&follower);
+            }
+        }
+    }
+
+    /// Quarantine a follower, suspending all forwarding to it until the duration elapses.
+    /// Durations too long to represent (such as [Duration::MAX]) never elapse.
+    /// Anything that was pending for it is discarded
+    fn quarantine_follower(&mut self, follower: NodeId, duration: Duration) {
+        if self.followers.binary_search(&follower).is_err() {
+            warn!("{:?} // Tried to quarantine follower {:?} which is not registered", self.own_id, follower);
+
+            return;
+        }
+
+        warn!("{:?} // Quarantining follower {:?} for {:?}", self.own_id, follower, duration);
+
+        self.quarantined.insert(follower, self.config.clock.now().checked_add(duration));
+        self.pending_batches.remove(&follower);
+    }
+
+    /// Lift the quarantines that have ended
+    fn release_expired_quarantines(&mut self) {
+        let now = self.config.clock.now();
+
+        self.quarantined.retain(|follower, until| {
+            let expired = until.map_or(false, |until| until <= now);
+
+            if expired {
+                info!("Quarantine of follower {:?} has ended, resuming forwarding", follower);
+            }
+
+            !expired
+        });
+    }
+
+    /// The registered followers which are not quarantined, in sorted order
+    fn active_followers(&self) -> Vec<NodeId> {
+        self.followers.iter()
+            .filter(|follower| !self.quarantined.contains_key(follower))
+            .copied()
+            .collect()
+    }
+
+    /// Forward one of our prepares or commits to all followers, holding it until its
+    /// sequence commits if we are configured to do so
+    fn forward_vote(&mut self, message: Arc<ReadOnly<E::Message>>) {
+        let seq = message.seq();
+
+        //The commit notice arrives through another channel, so our own vote may only be
+        //handled after its sequence has committed, in which case there is nothing to wait for
+        let committed = self.highest_committed.map_or(false, |committed| seq <= committed);
+
+        if self.config.forward_only_after_commit && !committed {
+            self.held_until_commit.entry(seq).or_default().push(message);
+
+            return;
+        }
+
+        let followers = self.active_followers();
+
+        self.record_forwarded(seq, &followers);
+
+        self.forward_to_followers(&message, followers.into_iter());
+    }
+
+    /// Forward all of the messages held for the sequence which has just been committed.
+    /// Since sequences commit in order, whatever is still held for previous sequences
+    /// belongs to sequences that did not commit, so it is discarded
+    fn release_held_messages(&mut self, committed: SeqNo) {
+        if self.highest_committed.map_or(true, |highest| committed > highest) {
+            self.highest_committed = Some(committed);
+        }
+
+        let still_held = self.held_until_commit.split_off(&committed.next());
+
+        let mut released = std::mem::replace(&mut self.held_until_commit, still_held);
+
+        let messages = released.remove(&committed).unwrap_or_default();
+
+        if !released.is_empty() {
+            debug!("{:?} // Discarding held messages for {} sequences which did not commit", self.own_id, released.len());
+        }
+
+        let followers = self.active_followers();
+
+        for message in messages {
+            self.record_forwarded(committed, &followers);
+
+            self.forward_to_followers(&message, followers.iter().copied());
+        }
+    }
+
+    /// Forward a message to the given followers, respecting the batching preference
+    /// of each of them. Followers that do not batch get the message immediately.
+    ///
+    /// The message is shared with the consensus thread, so it is only cloned
+    /// for each delivery which needs a copy of its own
+    fn forward_to_followers(&mut self, message: &Arc<ReadOnly<E::Message>>,
+                                    targets: impl Iterator<Item=NodeId>) {
+        let (batched, immediate): (Vec<NodeId>, Vec<NodeId>) = targets
+            .partition(|follower| self.batching_preferences.contains_key(follower));
+
+        for follower in batched {
+            let max_batch_size = self.batching_preferences[&follower].max_batch_size;
+
+            let now = self.config.clock.now();
+
+            let batch = self.pending_batches.entry(follower).or_insert_with(|| PendingBatch::new(now));
+
+            batch.messages.push(Self::into_forward_message(message));
+
+            if batch.messages.len() >= max_batch_size {
+                self.flush_batch(follower);
+            }
+        }
+
+        if immediate.is_empty() {
+            return;
+        }
+
+        let broadcast = self.transport.broadcast(Self::into_forward_message(message), immediate);
+
+        if let Err(failed) = broadcast {
+            warn!("{:?} // Failed to broadcast message to followers {:?}, retrying to send to each of them individually",
+                self.own_id, failed);
+
+            self.unicast_to_followers(message, failed);
+        }
+    }
+
+    /// Send a message to each of the followers individually, so a single bad follower
+    /// can't prevent the others from receiving it. Followers we fail to send to are
+    /// flagged as failing
+    fn unicast_to_followers(&mut self, message: &Arc<ReadOnly<E::Message>>, targets: Vec<NodeId>) {
+        for follower in targets {
+            self.send_to_follower(Self::into_forward_message(message), follower, true);
+        }
+    }
+
+    /// Send a message to a single follower, flagging it as failing (and keeping
+    /// the message as a dead letter) if the send fails
+    fn send_to_follower(&mut self, message: E::Message, follower: NodeId, flush: bool) {
+        let seq = message.seq();
+
+        match self.transport.send(message, follower, flush) {
+            Ok(_) => {
+                self.failing_followers.remove(&follower);
+            }
+            Err(err) => {
+                let failures = self.failing_followers.entry(follower).or_insert(0);
+
+                *failures += 1;
+
+                warn!("{:?} // Failed to send message to follower {:?} ({} consecutive failures): {:?}",
+                    self.own_id, follower, *failures, err);
+
+                self.push_dead_letter(DeadLetter {
+                    follower,
+                    seq,
+                    reason: format!("{:?}", err),
+                });
+            }
+        }
+    }
+
+    /// Store a forward we were unable to deliver, dropping the oldest one if we are full
+    fn push_dead_letter(&mut self, dead_letter: DeadLetter) {
+        if self.config.dead_letter_capacity == 0 {
+            metric_increment(FOLLOWER_DEAD_LETTERS_DROPPED_ID, Some(1));
+
+            return;
+        }
+
+        while self.dead_letters.len() >= self.config.dead_letter_capacity {
+            self.dead_letters.pop_front();
+
+            metric_increment(FOLLOWER_DEAD_LETTERS_DROPPED_ID, Some(1));
+        }
+
+        self.dead_letters.push_back(dead_letter);
+    }
+
+    /// Deliver all of the messages that are pending for a given follower,
+    /// only flushing the connection on the last one
+    fn flush_batch(&mut self, follower: NodeId) {
+        if let Some(batch) = self.pending_batches.remove(&follower) {
+            debug!("{:?} // Delivering batch of {} messages to follower {:?}", self.own_id, batch.messages.len(), follower);
+
+            let batch_len = batch.messages.len();
+
+            for (i, message) in batch.messages.into_iter().enumerate() {
+                let flush = i + 1 == batch_len;
+
+                self.send_to_follower(message, follower, flush);
+            }
+        }
+    }
+
+    /// Deliver the batches whose oldest message has waited for longer than
+    /// the follower is willing to wait
+    fn flush_expired_batches(&mut self) {
+        let expired: Vec<NodeId> = self.pending_batches.iter()
+            .filter(|(follower, batch)| {
+                self.batching_preferences.get(follower)
+                    .map_or(true, |pref| self.config.clock.elapsed_since(batch.first_queued) >= pref.max_linger)
+            })
+            .map(|(follower, _)| *follower)
+            .collect();
+
+        for follower in expired {
+            self.flush_batch(follower);
+        }
+    }
+
+    /// Calculate which followers we have to send the messages to
+    /// according to the disposition of the quorum and followers
+    ///
+    /// (This is only needed for the preprepare message, all others use
+    /// multicast)
+    fn targets(&self, view: &E::View) -> Vec<NodeId> {
+        let mut targetted_followers = self.selected_followers(view);
+
+        if let Some(max_followers) = self.config.max_followers_per_replica {
+            if targetted_followers.len() > max_followers {
+                let overflow = targetted_followers.len() - max_followers;
+
+                warn!("{:?} // Assigned {} followers but we can only serve {}, not serving {} of them. Consider adding more replicas",
+                    self.own_id, targetted_followers.len(), max_followers, overflow);
+
+                metric_increment(FOLLOWER_TARGETS_OVERFLOW_ID, Some(overflow as u64));
+
+                targetted_followers.truncate(max_followers);
+            }
+        }
+
+        targetted_followers
+    }
+
+    /// The followers selected by the target strategy, before applying our follower cap
+    fn selected_followers(&self, view: &E::View) -> Vec<NodeId> {
+        let leader_forwards = self.config.leader_forwards_preprepares;
+
+        let active_followers = self.active_followers();
+
+        //If we are not part of the forwarding layout of this view, we are not assigned anyone
+        let assigned = routing::forwarding_position(view, self.own_id, leader_forwards)
+            .map(|position| routing::followers_for_position(view, position, &active_followers, leader_forwards))
+            .unwrap_or_default();
+
+        let mut selected = self.target_strategy.select_targets(&TargetSelection {
+            own_id: self.own_id,
+            assigned: &assigned,
+            active_followers: &active_followers,
+            regions: &self.regions,
+        });
+
+        //Never forward the same message twice to a follower, whatever the strategy selected
+        let mut seen = BTreeSet::new();
+
+        selected.retain(|follower| seen.insert(*follower));
+
+        selected
+    }
+
+    /// Clone a message shared with the consensus thread into an owned message that
+    /// can be forwarded to the followers.
+    ///
+    /// We clone the messages here in this thread so we don't slow down the consensus
+    /// thread at all, and record how long it takes.
+    /// This is the only place messages are cloned, once for each delivery which needs
+    /// a copy of its own (a broadcast, a batched follower or a retried follower), so
+    /// the samples of the clone time also count the clones made
+    fn into_forward_message(message: &Arc<ReadOnly<E::Message>>) -> E::Message {
+        let start = Instant::now();
+
+        let message = message.clone_message();
+
+        metric_duration(FOLLOWER_FORWARD_CLONE_TIME_ID, start.elapsed());
+
+        message
+    }
+
+    /// Handle when we have received a preprepare message
+    fn handle_preprepare_msg_rcvd(
+        &mut self,
+        view: &E::View,
+        message: Arc<ReadOnly<E::Message>>,
+    ) {
+        let seq = message.seq();
+
+        if !Self::is_member(view, self.own_id) {
+            //We are not part of this view (for example, we are being reconfigured out),
+            //so we have no place in its routing and leave forwarding to its members
+            self.trace_routing_decision(seq, view, &[], Some(ForwardSkipReason::Spectator));
+
+            self.skipped(ForwardSkipReason::Spectator, seq, ForwardKind::PrePrepare);
+
+            return;
+        }
+
+        if view.primary() == self.own_id && !self.config.leader_forwards_preprepares {
+            //Leaders don't send pre_prepares to followers in order to save bandwidth
+            //as they already have to send the to all of the replicas
+            //(unless they have been configured to do so)
+            self.trace_routing_decision(seq, view, &[], Some(ForwardSkipReason::LeaderExempt));
+
+            self.skipped(ForwardSkipReason::LeaderExempt, seq, ForwardKind::PrePrepare);
+
+            return;
+        }
+
+        if let Some(schedule) = &self.config.leader_schedule {
+            if !schedule.is_scheduled(message.sender(), seq) {
+                warn!("{:?} // Received pre prepare for sequence {:?} from {:?}, which is not scheduled to propose it. Not forwarding",
+                    self.own_id, seq, message.sender());
+
+                self.trace_routing_decision(seq, view, &[], Some(ForwardSkipReason::OffSchedule));
+
+                self.skipped(ForwardSkipReason::OffSchedule, seq, ForwardKind::PrePrepare);
+
+                return;
+            }
+        }
+
+        let targets = self.targets(view);
+
+        self.trace_routing_decision(seq, view, &targets, None);
+
+        self.record_forwarded(seq, &targets);
+
+        self.forward_to_followers(&message, targets.into_iter());
+    }
+
+    /// Report a message we have decided not to forward to the skip observer, if there is one
+    fn skipped(&self, reason: ForwardSkipReason, seq: SeqNo, kind: ForwardKind) {
+        if let Some(observer) = &self.config.skip_observer {
+            observer.on_skip(reason, seq, kind);
+        }
+    }
+
+    /// If we have been asked to trace the routing of this sequence, record the decision
+    /// that was taken. When the whole message was skipped, all followers share the same reason
+    fn trace_routing_decision(&mut self, seq: SeqNo, view: &E::View, targets: &[NodeId],
+                              skipped_message: Option<ForwardSkipReason>) {
+        if !self.traced_sequences.remove(&seq) {
+            return;
+        }
+
+        let assigned = self.selected_followers(view);
+
+        let skipped = self.followers.iter()
+            .filter(|follower| !targets.contains(follower))
+            .map(|follower| {
+                let reason = if let Some(reason) = skipped_message {
+                    reason
+                } else if self.quarantined.contains_key(follower) {
+                    ForwardSkipReason::Quarantined
+                } else if assigned.contains(follower) {
+                    ForwardSkipReason::OverCapacity
+                } else {
+                    ForwardSkipReason::NotAssigned
+                };
+
+                (*follower, reason)
+            })
+            .collect();
+
+        self.routing_traces.insert(seq, RoutingTrace {
+            seq,
+            view: view.sequence_number(),
+            targets: targets.to_vec(),
+            skipped,
+        });
+    }
+
+    /// Handle us having sent a prepare message (notice how pre prepare are handled on reception
+    /// and prepare/commit are handled on sending, this is because we don't want the leader
+    /// to have to send the pre prepare to all followers but since these messages are very small,
+    /// it's fine for all replicas to broadcast it to followers)
+    fn handle_prepare_msg(
+        &mut self,
+        prepare: Arc<ReadOnly<E::Message>>,
+    ) {
+        let seq = prepare.seq();
+
+        if prepare.sender() != self.own_id {
+            //We only broadcast our own prepare messages, not other peoples
+            self.skipped(ForwardSkipReason::ForeignSender, seq, ForwardKind::Prepare);
+
+            return;
+        }
+
+        if self.is_under_pressure() {
+            //Prepares are the least important forward, as followers can still make progress
+            //with the pre prepare and commits, so shed them to relieve the ordering protocol
+            debug!("{:?} // Shedding prepare for sequence {:?} as our queue is backed up ({} events)",
+                self.own_id, seq, self.queued_events());
+
+            metric_increment(FOLLOWER_SHED_FORWARDS_ID, Some(1));
+
+            self.skipped(ForwardSkipReason::Shed, seq, ForwardKind::Prepare);
+
+            return;
+        }
+
+        self.forward_vote(prepare);
+    }
+
+    /// Is our event queue backed up enough that we should shed low priority forwards?
+    fn is_under_pressure(&self) -> bool {
+        self.config.shed_prepares_above.map_or(false, |threshold| self.queued_events() > threshold)
+    }
+
+    /// How many events are waiting to be handled, both in the channel and
+    /// in the backlog we have already drained from it
+    fn queued_events(&self) -> usize {
+        self.backlog_pending + self.rx.len()
+    }
+
+    /// Handle us having sent a commit message (notice how pre prepare are handled on reception
+    /// and prepare/commit are handled on sending, this is because we don't want the leader
+    /// to have to send the pre prepare to all followers but since these messages are very small,
+    /// it's fine for all replicas to broadcast it to followers)
+    fn handle_commit_msg(
+        &mut self,
+        commit: Arc<ReadOnly<E::Message>>,
+    ) {
+        if commit.sender() != self.own_id {
+            //Like with prepares, we only broadcast our own commit messages
+            self.skipped(ForwardSkipReason::ForeignSender, commit.seq(), ForwardKind::Commit);
+
+            return;
+        }
+
+        self.forward_vote(commit);
+    }
+
+    /// Handle a sync (view change) message, forwarding it to all followers.
+    ///
+    /// If we are configured to elect sync forwarders, only the elected f + 1 replicas
+    /// forward it, which keeps it resistant to message dropping while avoiding the
+    /// n-fold redundancy. Until we know of a view, we always forward.
+    /// Replicas which are not members of the latest view don't forward
+    fn handle_sync_msg(&mut self, msg: Arc<ReadOnly<E::Message>>) {
+        if let Some(view) = &self.current_view {
+            if !Self::is_member(view, self.own_id) {
+                self.skipped(ForwardSkipReason::Spectator, msg.seq(), ForwardKind::Sync);
+
+                return;
+            }
+        }
+
+        if self.config.elect_sync_forwarders {
+            if let Some(view) = &self.current_view {
+                //The sync message carries the view it is for, which may not be the one we are in yet
+                if !routing::is_sync_forwarder(view, self.own_id, msg.seq(), self.config.rotate_sync_forwarders) {
+                    self.skipped(ForwardSkipReason::NotSyncForwarder, msg.seq(), ForwardKind::Sync);
+
+                    return;
+                }
+            }
+        }
+
+        let followers = self.active_followers();
+
+        self.forward_to_followers(&msg, followers.into_iter());
+    }
 }
+#[cfg(test)]
+mod tests {
+    use crate::server::follower_handling::testing::{nodes, RecordingTransport, TestFollowerHandling, TestMessage, TestView};
 
-impl<D, OP, POP, NT> FollowersFollowing<D, OP, POP, NT> where
-    OP: OrderingProtocolMessage<D> + 'static,
-    POP: PermissionedOrderingProtocolMessage + 'static,
-    NT: Send + Sync + 'static {
-    /// Starts the follower handling thread and returns a cloneable handle that
-    /// can be used to deliver messages to it.
-    pub fn init_follower_handling<ST, LP>(id: NodeId, node: &Arc<NT>) -> FollowerHandle<D, OP, POP>
-        where D: ApplicationData + 'static,
-              ST: StateTransferMessage + 'static,
-              LP: LogTransferMessage<D, OP> + 'static,
-              NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        let (tx, rx) = channel::new_bounded_sync(1024);
-
-        let follower_handling = Self {
-            own_id: id,
-            followers: Vec::new(),
-            send_node: Arc::clone(node),
-            rx,
+    use super::*;
+
+    fn view() -> TestView {
+        TestView::new(0, 0, &[0, 1, 2, 3], 1)
+    }
+
+    /// The deliveries of the given sequence to each of the given followers
+    fn delivered(followers: &[NodeId], seq: u32) -> Vec<(NodeId, SeqNo)> {
+        followers.iter().map(|follower| (*follower, SeqNo::from(seq))).collect()
+    }
+
+    #[test]
+    fn stepping_forwards_each_event_as_it_is_handled() {
+        let view = view();
+        let followers = nodes(&[10, 11, 12]);
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11, 12]);
+
+        //Pre prepares only go to the followers the routing assigned to us
+        handling.consensus(&view, TestMessage::pre_prepare(0, 5));
+
+        let assigned = RoutingTable::compute(&view, &followers, false)
+            .followers_of(&NodeId::from(1u32))
+            .cloned()
+            .unwrap_or_default();
+
+        assert!(!assigned.is_empty());
+        assert_eq!(handling.transport.take(), delivered(&assigned, 5));
+
+        //Our own votes go to every follower, while the votes of others are not ours to forward
+        handling.consensus(&view, TestMessage::prepare(1, 5));
+        handling.consensus(&view, TestMessage::prepare(2, 5));
+
+        assert_eq!(handling.transport.take(), delivered(&followers, 5));
+
+        let TestFollowerHandling { events, control, mut stepper, .. } = handling;
+
+        drop((events, control));
+
+        assert!(!stepper.step());
+    }
+
+    #[test]
+    fn held_votes_do_not_count_towards_the_clone_cap() {
+        let view = view();
+
+        let config = FollowerHandlingConfig {
+            forward_only_after_commit: true,
+            max_retained_clone_bytes: Some(10),
+            ..Default::default()
         };
 
-        Self::start_thread::<ST, LP>(follower_handling);
+        let mut handling = TestFollowerHandling::new(1, view.clone(), config);
+
+        handling.register(&[10]);
+
+        //Hold far more than the cap allows, as the votes are waiting for their sequences to commit
+        handling.consensus(&view, TestMessage::sized_commit(1, 1, 100));
+        handling.consensus(&view, TestMessage::sized_commit(1, 2, 100));
 
-        FollowerHandle::new(tx)
+        //Both votes were still taken from the channel
+        assert_eq!(handling.state().held_until_commit.len(), 2);
+        assert!(handling.transport.take().is_empty());
+
+        handling.control.sequence_committed(SeqNo::from(1u32)).unwrap();
+        handling.control.sequence_committed(SeqNo::from(2u32)).unwrap();
+
+        handling.step();
+
+        assert!(handling.state().held_until_commit.is_empty());
+        assert_eq!(handling.transport.take(), vec![(NodeId::from(10u32), SeqNo::from(1u32)), (NodeId::from(10u32), SeqNo::from(2u32))]);
     }
 
-    fn start_thread<ST, LP>(self) where D: ApplicationData + 'static,
-                                        ST: StateTransferMessage + 'static,
-                                        LP: LogTransferMessage<D, OP> + 'static,
-                                        NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        std::thread::Builder::new()
-            .name(format!(
-                "Follower Handling Thread for node {:?}",
-                self.own_id
-            ))
-            .spawn(move || {
-                self.run::<ST, LP>();
-            })
-            .expect("Failed to launch follower handling thread!");
+    #[test]
+    fn dropping_the_control_handles_does_not_stop_forwarding() {
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11]);
+
+        let TestFollowerHandling { events, control, mut stepper, transport, .. } = handling;
+
+        drop(control);
+
+        assert!(stepper.step());
+
+        events.send(FollowerInput::Consensus(view.clone(), TestMessage::prepare(1, 3))).unwrap();
+
+        assert!(stepper.step());
+        assert_eq!(transport.take(), delivered(&nodes(&[10, 11]), 3));
+
+        //Only once the ordering protocol lets go of its handle do we stop
+        drop(events);
+
+        assert!(!stepper.step());
     }
 
-    fn run<ST, LP>(mut self)
-        where D: ApplicationData + 'static,
-              ST: StateTransferMessage + 'static,
-              LP: LogTransferMessage<D, OP> + 'static,
-              NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        loop {
-            let message = self.rx.recv().unwrap();
+    #[test]
+    fn sync_forwarders_rotate_with_the_view_of_the_sync_message() {
+        let view = view();
 
-            match message {
-                FollowerEvent::ReceivedConsensusMsg(view, consensus_msg) => {
-                    todo!()
-                }
-                FollowerEvent::ReceivedViewChangeMsg(view_change_msg) => {
-                    self.handle_sync_msg::<ST, LP>(view_change_msg)
-                }
-            }
-        }
+        let config = FollowerHandlingConfig {
+            elect_sync_forwarders: true,
+            rotate_sync_forwarders: true,
+            ..Default::default()
+        };
+
+        //Replica 2 is not elected for view 0, but is for view 1, which we have not entered yet
+        let mut handling = TestFollowerHandling::new(2, view, config);
+
+        handling.register(&[10, 11]);
+
+        handling.sync(TestMessage::sync(0, 0));
+
+        assert!(handling.transport.take().is_empty());
+
+        handling.sync(TestMessage::sync(0, 1));
+
+        assert_eq!(handling.transport.take(), delivered(&nodes(&[10, 11]), 1));
     }
 
-    /// Calculate which followers we have to send the messages to
-    /// according to the disposition of the quorum and followers
-    ///
-    /// (This is only needed for the preprepare message, all others use
-    /// multicast)
-    fn targets(&self, view: &POP::ViewInfo) -> Vec<NodeId> {
-        //How many replicas are not the leader?
-        let available_replicas = view.n() - 1;
-
-        //How many followers do we have to provide for
-        let followers = self.followers.len();
-
-        //We only need one pre prepare in reality, since it is signed by the current leader
-        //And can't be forged, but since we want to prevent message dropping attacks,
-        //We need to use f+1 replicas
-        let replicas_per_follower = view.f() + 1;
-
-        //We do not want to have spaces between each id so we don't get inconsistencies
-        //In how we arrange the replicas
-        //In this layout, we will always get 0, 1, 2 as IDs, independently of what the leader
-        //is
-        let temp_id = if self.own_id > view.primary() {
-            NodeId::from(self.own_id.id() - 1)
-        } else {
-            self.own_id
+    #[test]
+    fn backlogged_pre_prepares_go_ahead_of_votes() {
+        let view = view();
+
+        let config = FollowerHandlingConfig {
+            leader_forwards_preprepares: true,
+            ..Default::default()
         };
 
-        if followers >= available_replicas {
-            //How many followers do we have to forward the message to
-            //Taking all of this into account
-            let followers_for_replica = (replicas_per_follower * followers) / available_replicas;
+        //As the leader, both the pre prepare and the votes are our own
+        let mut handling = TestFollowerHandling::new(0, view.clone(), config);
 
-            let first_follower = temp_id.id() % (self.followers.len() as u32);
+        handling.register(&[10]);
 
-            let last_follower = first_follower + followers_for_replica as u32;
+        handling.events.send(FollowerInput::Consensus(view.clone(), TestMessage::commit(0, 1))).unwrap();
+        handling.events.send(FollowerInput::Consensus(view.clone(), TestMessage::prepare(0, 1))).unwrap();
+        handling.events.send(FollowerInput::Consensus(view.clone(), TestMessage::pre_prepare(0, 2))).unwrap();
 
-            let mut targetted_followers =
-                Vec::with_capacity((last_follower - first_follower) as usize);
+        handling.step();
 
-            for i in first_follower..=last_follower {
-                targetted_followers.push(self.followers[i as usize]);
-            }
+        let follower = nodes(&[10]);
 
-            targetted_followers
-        } else {
-            //TODO: How to handle layouts when there are more replicas than followers?
-            todo!()
+        assert_eq!(handling.transport.take(), [delivered(&follower, 2), delivered(&follower, 1), delivered(&follower, 1)].concat());
+    }
+
+    #[test]
+    fn lag_counts_from_the_first_sequence_forwarded_to_the_follower() {
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        //The follower joins long after the first sequence
+        handling.register(&[10]);
+
+        for seq in 100..103 {
+            handling.consensus(&view, TestMessage::prepare(1, seq));
         }
+
+        let follower = NodeId::from(10u32);
+
+        assert_eq!(handling.query(|control| control.follower_lag()), BTreeMap::from([(follower, 3)]));
+
+        handling.control.follower_acked(follower, SeqNo::from(101u32)).unwrap();
+
+        assert_eq!(handling.query(|control| control.follower_lag()), BTreeMap::from([(follower, 1)]));
     }
 
-    /// Handle when we have received a preprepare message
-    fn handle_preprepare_msg_rcvd<ST, LP>(
-        &mut self,
-        view: &POP::ViewInfo,
-        message: Arc<ReadOnly<StoredMessage<Protocol<OP::ProtocolMessage>>>>,
-    ) where D: ApplicationData + 'static,
-            ST: StateTransferMessage + 'static,
-            LP: LogTransferMessage<D, OP> + 'static,
-            NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        if view.primary() == self.own_id {
-            //Leaders don't send pre_prepares to followers in order to save bandwidth
-            //as they already have to send the to all of the replicas
-            return;
+    #[test]
+    fn quarantines_end_once_their_duration_elapses() {
+        let view = view();
+        let followers = nodes(&[10, 11]);
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11]);
+
+        handling.control.quarantine_follower(followers[0], Duration::from_secs(5)).unwrap();
+        handling.control.quarantine_follower(followers[1], Duration::MAX).unwrap();
+
+        handling.consensus(&view, TestMessage::prepare(1, 1));
+
+        assert!(handling.transport.take().is_empty());
+
+        handling.clock.advance(Duration::from_secs(5));
+
+        handling.consensus(&view, TestMessage::prepare(1, 2));
+
+        //The follower quarantined for too long to represent remains quarantined
+        assert_eq!(handling.transport.take(), delivered(&followers[..1], 2));
+        assert_eq!(handling.state().quarantined.get(&followers[1]), Some(&None));
+    }
+
+    #[test]
+    fn directed_forwards_to_unregistered_followers_are_refused() {
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(1, view, FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11]);
+
+        let err = handling.forward_to(&[10, 12], TestMessage::prepare(1, 4)).unwrap_err();
+
+        assert!(format!("{:?}", err).contains(&format!("{:?}", nodes(&[12]))), "{:?}", err);
+        assert!(handling.transport.take().is_empty());
+
+        //Duplicated targets are only forwarded to once
+        handling.forward_to(&[11, 10, 11], TestMessage::prepare(1, 5)).unwrap();
+
+        assert_eq!(handling.transport.take(), delivered(&nodes(&[10, 11]), 5));
+    }
+
+    fn commit_gated() -> FollowerHandlingConfig {
+        FollowerHandlingConfig {
+            forward_only_after_commit: true,
+            ..Default::default()
         }
+    }
 
-        //Clone the messages here in this thread so we don't slow down the consensus thread at all
-        let header = message.header().clone();
+    #[test]
+    fn votes_are_held_until_their_sequence_commits() {
+        let view = view();
+        let follower = nodes(&[10]);
 
-        let pre_prepare = message.message().clone();
+        let mut handling = TestFollowerHandling::new(1, view.clone(), commit_gated());
 
-        let message = SystemMessage::from_fwd_protocol_message(StoredMessage::new(header, pre_prepare));
+        handling.register(&[10]);
 
-        let targets = self.targets(view);
+        handling.consensus(&view, TestMessage::prepare(1, 1));
+        handling.consensus(&view, TestMessage::commit(1, 1));
+        handling.consensus(&view, TestMessage::prepare(1, 2));
 
-        self.send_node.broadcast(message, targets.into_iter());
+        assert!(handling.transport.take().is_empty());
+
+        handling.control.sequence_committed(SeqNo::from(1u32)).unwrap();
+        handling.step();
+
+        assert_eq!(handling.transport.take(), [delivered(&follower, 1), delivered(&follower, 1)].concat());
+        assert!(handling.state().held_until_commit.contains_key(&SeqNo::from(2u32)));
+
+        //A vote which only reaches us after its sequence has committed is not held
+        handling.consensus(&view, TestMessage::commit(1, 1));
+
+        assert_eq!(handling.transport.take(), delivered(&follower, 1));
     }
 
-    /// Handle us having sent a prepare message (notice how pre prepare are handled on reception
-    /// and prepare/commit are handled on sending, this is because we don't want the leader
-    /// to have to send the pre prepare to all followers but since these messages are very small,
-    /// it's fine for all replicas to broadcast it to followers)
-    fn handle_prepare_msg<ST, LP>(
-        &mut self,
-        prepare: Arc<ReadOnly<StoredMessage<Protocol<OP::ProtocolMessage>>>>,
-    ) where D: ApplicationData + 'static,
-            ST: StateTransferMessage + 'static,
-            LP: LogTransferMessage<D, OP> + 'static,
-            NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        if prepare.header().from() != self.own_id {
-            //We only broadcast our own prepare messages, not other peoples
-            return;
+    #[test]
+    fn held_votes_of_sequences_that_did_not_commit_are_dropped() {
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), commit_gated());
+
+        handling.register(&[10]);
+
+        handling.consensus(&view, TestMessage::prepare(1, 3));
+        handling.consensus(&view, TestMessage::prepare(1, 4));
+
+        handling.control.sequence_committed(SeqNo::from(4u32)).unwrap();
+        handling.step();
+
+        assert_eq!(handling.transport.take(), delivered(&nodes(&[10]), 4));
+        assert!(handling.state().held_until_commit.is_empty());
+    }
+
+    #[test]
+    fn region_coverage_adds_one_follower_of_each_uncovered_region() {
+        let active_followers = nodes(&[10, 11, 12, 13, 14]);
+
+        //Follower 14 is untagged, so it is not part of any region
+        let regions = BTreeMap::from([
+            (NodeId::from(10u32), "eu".to_string()),
+            (NodeId::from(11u32), "eu".to_string()),
+            (NodeId::from(12u32), "us".to_string()),
+            (NodeId::from(13u32), "us".to_string()),
+        ]);
+
+        let select = |own_id: u32| RegionCoverageStrategy.select_targets(&TargetSelection {
+            own_id: NodeId::from(own_id),
+            assigned: &nodes(&[10]),
+            active_followers: &active_followers,
+            regions: &regions,
+        });
+
+        //The assignment already covers "eu", and the extra follower of "us" depends on our id
+        assert_eq!(select(1), nodes(&[10, 13]));
+        assert_eq!(select(2), nodes(&[10, 12]));
+    }
+
+    #[test]
+    fn disabled_follower_handling_neither_forwards_nor_fails() {
+        let view = view();
+
+        let config = FollowerHandlingConfig {
+            enabled: false,
+            ..Default::default()
+        };
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), config);
+
+        handling.register(&[10]);
+
+        //More events than the channel holds, which are all discarded
+        for seq in 0..(FOLLOWER_CHANNEL_SIZE as u32 * 2) {
+            handling.consensus(&view, TestMessage::prepare(1, seq));
         }
 
-        let header = prepare.header().clone();
+        handling.forward_to(&[10], TestMessage::prepare(1, 0)).unwrap();
 
-        //Clone the messages here in this thread so we don't slow down the consensus thread at all
-        let prepare = prepare.message().clone();
+        assert!(!handling.query(|control| control.is_follower(NodeId::from(10u32))));
+        assert!(handling.transport.take().is_empty());
 
-        let message = SystemMessage::from_fwd_protocol_message(StoredMessage::new(header, prepare));
+        let TestFollowerHandling { events, mut stepper, .. } = handling;
 
-        self.send_node
-            .broadcast(message, self.followers.iter().copied());
+        drop(events);
+
+        assert!(!stepper.step());
     }
 
-    /// Handle us having sent a commit message (notice how pre prepare are handled on reception
-    /// and prepare/commit are handled on sending, this is because we don't want the leader
-    /// to have to send the pre prepare to all followers but since these messages are very small,
-    /// it's fine for all replicas to broadcast it to followers)
-    fn handle_commit_msg<ST, LP>(
-        &mut self,
-        commit: Arc<ReadOnly<StoredMessage<Protocol<OP::ProtocolMessage>>>>,
-    ) where D: ApplicationData + 'static,
-            ST: StateTransferMessage + 'static,
-            LP: LogTransferMessage<D, OP> + 'static,
-            NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        if commit.header().from() != self.own_id {
-            //Like with prepares, we only broadcast our own commit messages
-            return;
+    #[test]
+    fn fractional_coverage_is_not_truncated_in_the_metric() {
+        let coverage = ObservedCoverage { sequences: 3, forwarders: 5 };
+
+        assert_eq!(ObservedCoverage::metric_value(coverage.average()), 167);
+        assert_eq!(ObservedCoverage::metric_value(1.999), 200);
+        assert_eq!(ObservedCoverage::metric_value(0.0), 0);
+    }
+
+    /// The followers the routing assigns to the given replica
+    fn assigned_to(view: &TestView, followers: &[NodeId], replica: u32, leader_forwards: bool) -> Vec<NodeId> {
+        RoutingTable::compute(view, followers, leader_forwards)
+            .followers_of(&NodeId::from(replica))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn followers_over_the_cap_are_not_served() {
+        let view = view();
+        let followers = nodes(&[10, 11, 12, 13, 14, 15]);
+
+        let config = FollowerHandlingConfig {
+            max_followers_per_replica: Some(2),
+            ..Default::default()
+        };
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), config);
+
+        handling.register(&[10, 11, 12, 13, 14, 15]);
+
+        let assigned = assigned_to(&view, &followers, 1, false);
+
+        assert!(assigned.len() > 2);
+
+        handling.control.trace_routing(SeqNo::from(5u32)).unwrap();
+
+        handling.consensus(&view, TestMessage::pre_prepare(0, 5));
+
+        assert_eq!(handling.transport.take(), delivered(&assigned[..2], 5));
+
+        //The overflow is reported as such, not as followers that were never ours
+        let trace = handling.query(|control| control.take_routing_trace(SeqNo::from(5u32))).unwrap();
+
+        let over_capacity: Vec<NodeId> = trace.skipped.iter()
+            .filter(|(_, reason)| *reason == ForwardSkipReason::OverCapacity)
+            .map(|(follower, _)| *follower)
+            .collect();
+
+        assert_eq!(over_capacity, assigned[2..]);
+    }
+
+    #[test]
+    fn a_failing_follower_does_not_keep_the_others_from_receiving_a_forward() {
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11, 12]);
+
+        handling.transport.fail(11);
+
+        handling.consensus(&view, TestMessage::prepare(1, 4));
+
+        assert_eq!(handling.transport.take(), delivered(&nodes(&[10, 12]), 4));
+
+        let failing = handling.query(|control| control.failing_followers());
+
+        assert_eq!(failing, BTreeMap::from([(NodeId::from(11u32), 1)]));
+
+        let dead_letters = handling.query(|control| control.dead_letters());
+
+        assert_eq!(dead_letters.iter().map(|letter| (letter.follower, letter.seq)).collect::<Vec<_>>(),
+                   delivered(&nodes(&[11]), 4));
+    }
+
+    #[test]
+    fn heartbeats_clear_the_suspicion_of_a_silent_follower() {
+        let interval = Duration::from_secs(1);
+        let grace = Duration::from_millis(500);
+
+        let config = FollowerHandlingConfig {
+            follower_liveness_interval: Some(interval),
+            follower_eviction_grace: grace,
+            ..Default::default()
+        };
+
+        let mut handling = TestFollowerHandling::new(1, view(), config);
+
+        handling.register(&[10, 11]);
+
+        let suspected = |handling: &TestFollowerHandling, follower: u32| {
+            handling.state().liveness[&NodeId::from(follower)].suspected_since.is_some()
+        };
+
+        //Both followers miss an interval
+        handling.clock.advance(interval);
+        handling.step();
+
+        assert!(suspected(&handling, 10));
+        assert!(suspected(&handling, 11));
+
+        //Only follower 10 is heard from again before the grace period ends
+        handling.control.follower_heartbeat(NodeId::from(10u32)).unwrap();
+        handling.step();
+
+        assert!(!suspected(&handling, 10));
+
+        handling.clock.advance(grace);
+        handling.step();
+
+        assert!(handling.query(|control| control.is_follower(NodeId::from(10u32))));
+        assert!(!handling.query(|control| control.is_follower(NodeId::from(11u32))));
+    }
+
+    #[test]
+    fn the_routing_trace_matches_the_forward() {
+        let view = view();
+        let followers = nodes(&[10, 11, 12, 13]);
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11, 12, 13]);
+        handling.control.quarantine_follower(NodeId::from(13u32), Duration::from_secs(60)).unwrap();
+        handling.control.trace_routing(SeqNo::from(7u32)).unwrap();
+
+        handling.consensus(&view, TestMessage::pre_prepare(0, 7));
+
+        let forwarded: Vec<NodeId> = handling.transport.take().into_iter()
+            .map(|(follower, _)| follower)
+            .collect();
+
+        assert!(!forwarded.is_empty());
+
+        let trace = handling.query(|control| control.take_routing_trace(SeqNo::from(7u32))).unwrap();
+
+        assert_eq!(trace.seq, SeqNo::from(7u32));
+        assert_eq!(trace.view, view.sequence_number());
+        assert_eq!(trace.targets, forwarded);
+
+        //Every other follower is accounted for, with the reason it was skipped
+        let active = nodes(&[10, 11, 12]);
+        let assigned = assigned_to(&view, &active, 1, false);
+
+        let mut expected: Vec<(NodeId, ForwardSkipReason)> = followers.iter()
+            .filter(|follower| !forwarded.contains(follower))
+            .map(|follower| {
+                let reason = if *follower == NodeId::from(13u32) {
+                    ForwardSkipReason::Quarantined
+                } else {
+                    assert!(!assigned.contains(follower));
+
+                    ForwardSkipReason::NotAssigned
+                };
+
+                (*follower, reason)
+            })
+            .collect();
+
+        expected.sort_by_key(|(follower, _)| *follower);
+
+        assert_eq!(trace.skipped, expected);
+
+        //Traces are only taken once, and only for the sequences asked for
+        handling.consensus(&view, TestMessage::pre_prepare(0, 8));
+
+        assert!(handling.query(|control| control.take_routing_trace(SeqNo::from(7u32))).is_none());
+        assert!(handling.query(|control| control.take_routing_trace(SeqNo::from(8u32))).is_none());
+    }
+
+    #[test]
+    fn prepares_are_shed_while_the_queue_is_backed_up() {
+        let view = view();
+
+        let config = FollowerHandlingConfig {
+            shed_prepares_above: Some(2),
+            ..Default::default()
+        };
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), config);
+
+        handling.register(&[10]);
+
+        //Back the queue up with our own votes before the follower handling gets to them
+        for seq in 1..=5 {
+            handling.events.send(FollowerInput::Consensus(view.clone(), TestMessage::prepare(1, seq))).unwrap();
+            handling.events.send(FollowerInput::Consensus(view.clone(), TestMessage::commit(1, seq))).unwrap();
         }
 
-        let header = commit.header().clone();
-        let commit = commit.message().clone();
+        handling.step();
+
+        let follower = NodeId::from(10u32);
 
-        let message = SystemMessage::from_fwd_protocol_message(StoredMessage::new(header, commit));
+        //The prepares are handled first, while the commits are still queued behind them,
+        //so they are all shed. Commits are never shed
+        let commits: Vec<(NodeId, SeqNo)> = (1..=5u32)
+            .map(|seq| (follower, SeqNo::from(seq)))
+            .collect();
 
-        self.send_node
-            .broadcast(message, self.followers.iter().copied());
+        assert_eq!(handling.transport.take(), commits);
+
+        //Once the queue has drained, prepares are forwarded again
+        handling.consensus(&view, TestMessage::prepare(1, 6));
+
+        assert_eq!(handling.transport.take(), delivered(&[follower], 6));
     }
 
-    ///
-    fn handle_sync_msg<ST, LP>(&mut self, msg: Arc<ReadOnly<StoredMessage<Protocol<OP::ProtocolMessage>>>>)
-        where D: ApplicationData + 'static,
-              ST: StateTransferMessage + 'static,
-              LP: LogTransferMessage<D, OP> + 'static,
-              NT: ProtocolNetworkNode<Service<D, OP, ST, LP>> {
-        let header = msg.header().clone();
-        let message = msg.message().clone();
+    /// A skip observer which panics, standing in for a handler that fails on a malformed message
+    struct PanickingObserver;
+
+    impl ForwardSkipObserver for PanickingObserver {
+        fn on_skip(&self, _reason: ForwardSkipReason, seq: SeqNo, _kind: ForwardKind) {
+            panic!("failed to handle the skip of sequence {:?}", seq);
+        }
+    }
+
+    #[test]
+    fn a_panicking_event_does_not_stop_the_follower_handling() {
+        let view = view();
+
+        let config = FollowerHandlingConfig {
+            isolate_event_panics: true,
+            skip_observer: Some(Box::new(PanickingObserver)),
+            ..Default::default()
+        };
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), config);
+
+        handling.register(&[10]);
+
+        //The prepare of another replica is skipped, which panics
+        handling.consensus(&view, TestMessage::prepare(2, 3));
+
+        //The following events are still handled
+        handling.consensus(&view, TestMessage::prepare(1, 3));
+        handling.consensus(&view, TestMessage::commit(1, 3));
+
+        let follower = NodeId::from(10u32);
+
+        assert_eq!(handling.transport.take(), vec![(follower, SeqNo::from(3u32)), (follower, SeqNo::from(3u32))]);
+    }
 
-        let network_msg = SystemMessage::from_fwd_protocol_message(StoredMessage::new(header, message));
+    #[test]
+    fn the_highest_forwarded_sequence_advances_with_consensus_forwards() {
+        let view = view();
 
-        self.send_node.broadcast(network_msg, self.followers.iter().copied());
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10]);
+
+        assert_eq!(handling.query(|control| control.highest_forwarded_seq()), None);
+
+        handling.consensus(&view, TestMessage::prepare(1, 3));
+
+        assert_eq!(handling.query(|control| control.highest_forwarded_seq()), Some(SeqNo::from(3u32)));
+
+        handling.consensus(&view, TestMessage::commit(1, 4));
+
+        assert_eq!(handling.query(|control| control.highest_forwarded_seq()), Some(SeqNo::from(4u32)));
+
+        //Late forwards of older sequences don't move it back, and sync messages carry views instead
+        handling.consensus(&view, TestMessage::commit(1, 3));
+        handling.sync(TestMessage::sync(1, 9));
+
+        assert_eq!(handling.transport.take().len(), 4);
+        assert_eq!(handling.query(|control| control.highest_forwarded_seq()), Some(SeqNo::from(4u32)));
+    }
+
+    #[test]
+    fn views_we_are_not_a_member_of_are_only_spectated() {
+        //We are replica 5, which is not part of the view
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(5, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10, 11, 12]);
+        handling.control.trace_routing(SeqNo::from(2u32)).unwrap();
+
+        handling.consensus(&view, TestMessage::pre_prepare(0, 2));
+        handling.sync(TestMessage::sync(0, 1));
+
+        assert!(handling.transport.take().is_empty());
+
+        let trace = handling.query(|control| control.take_routing_trace(SeqNo::from(2u32))).unwrap();
+
+        assert!(trace.targets.is_empty());
+        assert_eq!(trace.skipped, nodes(&[10, 11, 12]).into_iter()
+            .map(|follower| (follower, ForwardSkipReason::Spectator))
+            .collect::<Vec<_>>());
+
+        //Nothing is assigned to us, and we agree with the members on their assignments
+        let table = handling.query(|control| control.recompute_assignments()).unwrap();
+
+        assert!(table.followers_of(&NodeId::from(5u32)).is_none());
+        assert_eq!(table, RoutingTable::compute(&view, &nodes(&[10, 11, 12]), false));
+    }
+
+    #[test]
+    fn forwards_after_replacing_the_transport_go_through_the_new_one() {
+        let view = view();
+
+        let mut handling = TestFollowerHandling::new(1, view.clone(), FollowerHandlingConfig::default());
+
+        handling.register(&[10]);
+
+        //Follower 11 batches its deliveries in pairs
+        let batching = FollowerBatchingPreference { max_batch_size: 2, max_linger: Duration::from_secs(60) };
+
+        handling.control.register_follower(NodeId::from(11u32), batching).unwrap();
+
+        handling.consensus(&view, TestMessage::prepare(1, 1));
+
+        let old_transport = handling.transport.clone();
+
+        assert_eq!(old_transport.take(), delivered(&nodes(&[10]), 1));
+
+        let new_transport = RecordingTransport::default();
+
+        handling.forward.replace_transport(new_transport.clone()).unwrap();
+
+        handling.consensus(&view, TestMessage::prepare(1, 2));
+
+        //The batch that was pending when the transport was replaced is also delivered through the new one
+        assert!(old_transport.take().is_empty());
+        assert_eq!(new_transport.take(), vec![
+            (NodeId::from(11u32), SeqNo::from(1u32)),
+            (NodeId::from(11u32), SeqNo::from(2u32)),
+            (NodeId::from(10u32), SeqNo::from(2u32)),
+        ]);
     }
-}
\ No newline at end of file
+}
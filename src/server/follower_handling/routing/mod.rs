@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
+
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+
+/// The position of a replica in the pre prepare forwarding layout of a given view.
+///
+/// We do not want to have spaces between each id so we don't get inconsistencies
+/// In how we arrange the replicas
+/// In this layout, we will always get 0, 1, 2 as IDs, independently of what the leader
+/// is (unless the leader also forwards, in which case it keeps its own position).
+/// The position is the rank of the replica in the sorted membership, so ids do not have
+/// to be contiguous (as they won't be after reconfigurations).
+/// Returns `None` if the replica is not part of the layout
+pub(super) fn forwarding_position<V>(view: &V, replica: NodeId, leader_forwards: bool) -> Option<u32>
+    where V: NetworkView {
+    let mut forwarders: Vec<NodeId> = view.quorum_members().iter()
+        .filter(|member| leader_forwards || **member != view.primary())
+        .copied()
+        .collect();
+
+    forwarders.sort();
+
+    forwarders.binary_search(&replica).ok().map(|rank| rank as u32)
+}
+
+/// How many replicas are available to forward pre prepares in the given view
+pub(super) fn available_forwarders<V>(view: &V, leader_forwards: bool) -> usize
+    where V: NetworkView {
+    if leader_forwards {
+        view.n()
+    } else {
+        view.n().saturating_sub(1)
+    }
+}
+
+/// Calculate which followers the replica in the given position has to forward
+/// pre prepares to, according to the disposition of the quorum and followers
+pub(super) fn followers_for_position<V>(view: &V, position: u32, followers: &[NodeId], leader_forwards: bool) -> Vec<NodeId>
+    where V: NetworkView {
+    //How many replicas are not the leader? (If the leader also forwards,
+    //then all replicas are available)
+    let available_replicas = available_forwarders(view, leader_forwards);
+
+    //We only need one pre prepare in reality, since it is signed by the current leader
+    //And can't be forged, but since we want to prevent message dropping attacks,
+    //We need to use f+1 replicas (or as many as we have, if the view is too small)
+    let replicas_per_follower = (view.f() + 1).min(available_replicas);
+
+    if followers.is_empty() || available_replicas == 0 {
+        return Vec::new();
+    }
+
+    //How many forwards do we need in total, so that every follower
+    //is covered by replicas_per_follower replicas
+    let total_forwards = replicas_per_follower * followers.len();
+
+    //Divide these forwards evenly between the available replicas. When they don't
+    //divide evenly, the first `remainder` positions take one extra follower, so the
+    //coverage is exact and does not depend on the integer division
+    let base_forwards = total_forwards / available_replicas;
+    let remainder = total_forwards % available_replicas;
+
+    let position = position as usize;
+
+    let followers_for_replica = base_forwards + if position < remainder { 1 } else { 0 };
+
+    //Each position takes a contiguous block of the (wrapping) follower list, starting
+    //where the block of the previous position ended
+    let first_follower = position * base_forwards + position.min(remainder);
+
+    //Since replicas_per_follower never exceeds the available replicas, a block never
+    //wraps over itself, but make sure the same follower is never taken twice
+    let followers_for_replica = followers_for_replica.min(followers.len());
+
+    let mut targetted_followers = Vec::with_capacity(followers_for_replica);
+
+    for i in 0..followers_for_replica {
+        targetted_followers.push(followers[(first_follower + i) % followers.len()]);
+    }
+
+    targetted_followers
+}
+
+/// Is the given replica one of the f + 1 replicas of the given view elected to forward
+/// the sync messages of `sync_view` to the followers?
+///
+/// All replicas (including the leader) take part, using the same compacted layout
+/// as the pre prepare routing, so that every replica elects the same subset.
+/// When rotating, the elected window starts at a position derived from the view number
+/// carried by the sync message, so every replica that receives it elects the same subset
+/// (whatever view it is currently in) and the burden moves as the view changes
+pub(super) fn is_sync_forwarder<V>(view: &V, replica: NodeId, sync_view: SeqNo, rotate: bool) -> bool
+    where V: NetworkView {
+    let members = view.quorum_members().len();
+
+    let position = match forwarding_position(view, replica, true) {
+        Some(position) => position as usize,
+        None => return false,
+    };
+
+    let first_elected = if rotate {
+        u32::from(sync_view) as usize % members
+    } else {
+        0
+    };
+
+    //The distance from the start of the window, wrapping around the membership
+    let offset = (position + members - first_elected) % members;
+
+    offset < (view.f() + 1).min(members)
+}
+
+/// Why a message was not forwarded to a given follower
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForwardSkipReason {
+    /// We are the leader, which is exempt from forwarding pre prepares
+    LeaderExempt,
+    /// The pre prepare is not within the range of its leader's schedule
+    OffSchedule,
+    /// The follower is quarantined
+    Quarantined,
+    /// The follower is assigned to other replicas in this view
+    NotAssigned,
+    /// The follower was assigned to us, but over our cap of followers
+    OverCapacity,
+    /// The message was sent by another replica, and we only forward our own votes
+    ForeignSender,
+    /// The message was shed as our queue was backed up
+    Shed,
+    /// We were not elected to forward sync messages in this view
+    NotSyncForwarder,
+    /// We are not a member of the view, so we are only spectating it
+    Spectator,
+}
+
+/// The full routing decision taken for a single forwarded message
+#[derive(Clone, Debug)]
+pub struct RoutingTrace {
+    /// The sequence number of the traced message
+    pub seq: SeqNo,
+    /// The sequence number of the view used to route it
+    pub view: SeqNo,
+    /// The followers the message was forwarded to
+    pub targets: Vec<NodeId>,
+    /// The registered followers the message was not forwarded to, and why
+    pub skipped: Vec<(NodeId, ForwardSkipReason)>,
+}
+
+/// The followers that are assigned to each of the replicas of a given view.
+///
+/// The routing only depends on the view and on the (sorted) follower set, so
+/// every correct replica should produce exactly the same table. Tables can therefore
+/// be exported and compared across replicas to confirm that they agree.
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutingTable {
+    view: SeqNo,
+    assignments: BTreeMap<NodeId, Vec<NodeId>>,
+}
+
+impl RoutingTable {
+    /// Compute the routing table for the given view and followers
+    pub fn compute<V>(view: &V, followers: &[NodeId], leader_forwards: bool) -> Self
+        where V: NetworkView + Orderable {
+        let mut followers = followers.to_vec();
+
+        followers.sort();
+        followers.dedup();
+
+        let assignments = view.quorum_members().iter()
+            .filter(|replica| leader_forwards || **replica != view.primary())
+            .filter_map(|replica| {
+                let position = forwarding_position(view, *replica, leader_forwards)?;
+
+                Some((*replica, followers_for_position(view, position, &followers, leader_forwards)))
+            })
+            .collect();
+
+        Self {
+            view: view.sequence_number(),
+            assignments,
+        }
+    }
+
+    /// The sequence number of the view this table was computed for
+    pub fn view(&self) -> SeqNo {
+        self.view
+    }
+
+    /// The followers that are assigned to each replica
+    pub fn assignments(&self) -> &BTreeMap<NodeId, Vec<NodeId>> {
+        &self.assignments
+    }
+
+    /// The followers that are assigned to a given replica
+    pub fn followers_of(&self, replica: &NodeId) -> Option<&Vec<NodeId>> {
+        self.assignments.get(replica)
+    }
+
+    /// The replicas that are expected to forward pre prepares to the given follower.
+    ///
+    /// Since the routing only depends on the view and the follower set, a follower can
+    /// compute this table itself and compare it with the replicas it actually received a
+    /// pre prepare from, in order to detect which forwarders are dropping messages
+    pub fn forwarders_of(&self, follower: &NodeId) -> Vec<NodeId> {
+        self.assignments.iter()
+            .filter(|(_, followers)| followers.contains(follower))
+            .map(|(replica, _)| *replica)
+            .collect()
+    }
+
+    /// A digest of this table, which is cheap to exchange between replicas in order
+    /// to confirm they all computed the same assignments for a view.
+    /// Replicas with diverging digests either have a different view of the membership
+    /// or have hit a routing bug
+    pub fn digest(&self) -> Digest {
+        let mut ctx = Context::new();
+
+        ctx.update(&u32::from(self.view).to_le_bytes());
+
+        for (replica, followers) in &self.assignments {
+            ctx.update(&replica.id().to_le_bytes());
+            ctx.update(&(followers.len() as u32).to_le_bytes());
+
+            for follower in followers {
+                ctx.update(&follower.id().to_le_bytes());
+            }
+        }
+
+        ctx.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::follower_handling::testing::{nodes, TestView};
+
+    use super::*;
+
+    #[test]
+    fn identical_inputs_produce_identical_tables() {
+        let view = TestView::new(3, 0, &[0, 1, 2, 3], 1);
+
+        let first = RoutingTable::compute(&view, &nodes(&[10, 11, 12, 13, 14]), false);
+        let second = RoutingTable::compute(&view.clone(), &nodes(&[14, 12, 10, 13, 11]), false);
+
+        assert_eq!(first, second);
+        assert_eq!(format!("{:?}", first), format!("{:?}", second));
+    }
+
+    #[test]
+    fn remainder_is_distributed_for_exact_coverage() {
+        //3 forwarders and 4 followers need 2 * 4 = 8 forwards, which leaves a remainder of 2
+        let view = TestView::new(0, 0, &[0, 1, 2, 3], 1);
+        let followers = nodes(&[10, 11, 12, 13]);
+
+        let table = RoutingTable::compute(&view, &followers, false);
+
+        let forwards: usize = table.assignments().values().map(Vec::len).sum();
+
+        assert_eq!(forwards, 8);
+
+        for follower in &followers {
+            assert_eq!(table.forwarders_of(follower).len(), view.f() + 1, "follower {:?}", follower);
+        }
+    }
+
+    fn sync_forwarders(view: &TestView, rotate: bool) -> Vec<NodeId> {
+        view.quorum_members().iter()
+            .filter(|replica| is_sync_forwarder(view, **replica, view.sequence_number(), rotate))
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn only_elected_replicas_forward_sync_messages() {
+        let view = TestView::new(0, 0, &[0, 1, 2, 3], 1);
+
+        assert_eq!(sync_forwarders(&view, false), nodes(&[0, 1]));
+        assert!(!is_sync_forwarder(&view, NodeId::from(7u32), view.sequence_number(), false));
+    }
+
+    #[test]
+    fn non_contiguous_ids_get_unique_positions() {
+        let view = TestView::new(0, 4, &[1, 4, 9, 12], 1);
+
+        let mut positions: Vec<u32> = view.quorum_members().iter()
+            .filter_map(|replica| forwarding_position(&view, *replica, false))
+            .collect();
+
+        positions.sort();
+
+        assert_eq!(positions, vec![0, 1, 2]);
+        assert_eq!(forwarding_position(&view, NodeId::from(4u32), false), None);
+
+        let followers = nodes(&[20, 21, 22]);
+
+        let table = RoutingTable::compute(&view, &followers, false);
+
+        for follower in &followers {
+            assert_eq!(table.forwarders_of(follower).len(), view.f() + 1, "follower {:?}", follower);
+        }
+    }
+
+    #[test]
+    fn digests_match_only_for_identical_inputs() {
+        let view = TestView::new(5, 0, &[0, 1, 2, 3], 1);
+        let followers = nodes(&[10, 11, 12]);
+
+        let digest = RoutingTable::compute(&view, &followers, false).digest();
+
+        assert_eq!(digest, RoutingTable::compute(&view.clone(), &followers, false).digest());
+
+        let other_followers = RoutingTable::compute(&view, &nodes(&[10, 11, 13]), false).digest();
+        let other_view = RoutingTable::compute(&TestView::new(6, 1, &[0, 1, 2, 3], 1), &followers, false).digest();
+
+        assert_ne!(digest, other_followers);
+        assert_ne!(digest, other_view);
+    }
+
+    #[test]
+    fn tight_wrap_has_no_duplicate_targets() {
+        //Each forwarder takes as many forwards as there are followers, wrapping
+        //around the (very small) follower list
+        let view = TestView::new(0, 0, &[0, 1, 2, 3], 3);
+        let followers = nodes(&[10, 11]);
+
+        for position in 0..3 {
+            let mut targets = followers_for_position(&view, position, &followers, false);
+
+            let assigned = targets.len();
+
+            targets.sort();
+            targets.dedup();
+
+            assert_eq!(targets.len(), assigned, "position {}", position);
+        }
+
+        let table = RoutingTable::compute(&view, &followers, false);
+
+        for follower in &followers {
+            assert_eq!(table.forwarders_of(follower).len(), 3, "follower {:?}", follower);
+        }
+    }
+
+    #[test]
+    fn sync_forwarders_rotate_across_views() {
+        let first = TestView::new(0, 0, &[0, 1, 2, 3], 1);
+        let second = TestView::new(1, 1, &[0, 1, 2, 3], 1);
+
+        let first_forwarders = sync_forwarders(&first, true);
+        let second_forwarders = sync_forwarders(&second, true);
+
+        assert_eq!(first_forwarders.len(), first.f() + 1);
+        assert_eq!(second_forwarders.len(), second.f() + 1);
+        assert_ne!(first_forwarders, second_forwarders);
+
+        //Without rotation, the same replicas are always elected
+        assert_eq!(sync_forwarders(&first, false), sync_forwarders(&second, false));
+    }
+
+    #[test]
+    fn leader_keeps_its_position_when_it_forwards() {
+        let view = TestView::new(0, 1, &[0, 1, 2, 3], 1);
+        let leader = NodeId::from(1u32);
+
+        assert_eq!(forwarding_position(&view, leader, false), None);
+        assert_eq!(forwarding_position(&view, leader, true), Some(1));
+        assert_eq!(forwarding_position(&view, NodeId::from(3u32), true), Some(3));
+
+        //4 followers need 2 * 4 = 8 forwards, which the 4 replicas split evenly
+        let followers = nodes(&[10, 11, 12, 13]);
+
+        let table = RoutingTable::compute(&view, &followers, true);
+
+        for replica in view.quorum_members() {
+            assert_eq!(table.followers_of(replica).map(Vec::len), Some(2), "replica {:?}", replica);
+        }
+
+        for follower in &followers {
+            assert_eq!(table.forwarders_of(follower).len(), view.f() + 1, "follower {:?}", follower);
+        }
+    }
+}
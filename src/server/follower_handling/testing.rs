@@ -0,0 +1,277 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use atlas_common::channel::ChannelSyncTx;
+use atlas_common::error::*;
+use atlas_common::globals::ReadOnly;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
+
+use crate::clock::ManualClock;
+use crate::config::FollowerHandlingConfig;
+use crate::server::follower_handling::{ConsensusPhase, FollowerBatchingPreference, FollowerControlHandle,
+                                       FollowerForwardHandle, FollowerInput, FollowerStepper, FollowerTransport,
+                                       FollowersFollowing, ForwardedMessage, StepperState};
+
+/// A view with an explicit membership, leader and fault tolerance
+#[derive(Clone, Debug)]
+pub(super) struct TestView {
+    seq: SeqNo,
+    primary: NodeId,
+    members: Vec<NodeId>,
+    f: usize,
+}
+
+impl TestView {
+    pub(super) fn new(seq: u32, primary: u32, members: &[u32], f: usize) -> Self {
+        Self {
+            seq: SeqNo::from(seq),
+            primary: NodeId::from(primary),
+            members: nodes(members),
+            f,
+        }
+    }
+}
+
+impl Orderable for TestView {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl NetworkView for TestView {
+    fn primary(&self) -> NodeId {
+        self.primary
+    }
+
+    fn quorum(&self) -> usize {
+        2 * self.f + 1
+    }
+
+    fn quorum_members(&self) -> &Vec<NodeId> {
+        &self.members
+    }
+
+    fn f(&self) -> usize {
+        self.f
+    }
+
+    fn n(&self) -> usize {
+        self.members.len()
+    }
+}
+
+pub(super) fn nodes(ids: &[u32]) -> Vec<NodeId> {
+    ids.iter().copied().map(NodeId::from).collect()
+}
+
+/// A message carrying only what the follower handling looks at
+#[derive(Clone, Debug)]
+pub(super) struct TestMessage {
+    from: NodeId,
+    seq: SeqNo,
+    phase: ConsensusPhase,
+    len: usize,
+}
+
+impl TestMessage {
+    fn new(from: u32, seq: u32, phase: ConsensusPhase) -> Self {
+        Self {
+            from: NodeId::from(from),
+            seq: SeqNo::from(seq),
+            phase,
+            len: 0,
+        }
+    }
+
+    pub(super) fn pre_prepare(from: u32, seq: u32) -> Arc<ReadOnly<Self>> {
+        Self::new(from, seq, ConsensusPhase::PrePrepare).shared()
+    }
+
+    pub(super) fn prepare(from: u32, seq: u32) -> Arc<ReadOnly<Self>> {
+        Self::new(from, seq, ConsensusPhase::Prepare).shared()
+    }
+
+    pub(super) fn commit(from: u32, seq: u32) -> Arc<ReadOnly<Self>> {
+        Self::new(from, seq, ConsensusPhase::Commit).shared()
+    }
+
+    /// A sync message for the given view (the phase of sync messages is never looked at)
+    pub(super) fn sync(from: u32, view: u32) -> Arc<ReadOnly<Self>> {
+        Self::new(from, view, ConsensusPhase::PrePrepare).shared()
+    }
+
+    /// A commit whose payload is `len` bytes long
+    pub(super) fn sized_commit(from: u32, seq: u32, len: usize) -> Arc<ReadOnly<Self>> {
+        Self { len, ..Self::new(from, seq, ConsensusPhase::Commit) }.shared()
+    }
+
+    fn shared(self) -> Arc<ReadOnly<Self>> {
+        Arc::new(ReadOnly::new(self))
+    }
+}
+
+impl ForwardedMessage for TestMessage {
+    fn sender(&self) -> NodeId {
+        self.from
+    }
+
+    fn seq(&self) -> SeqNo {
+        self.seq
+    }
+
+    fn payload_len(&self) -> usize {
+        self.len
+    }
+
+    fn clone_message(&self) -> Self {
+        self.clone()
+    }
+}
+
+fn classify(message: &TestMessage) -> ConsensusPhase {
+    message.phase
+}
+
+/// A transport which records every delivery, and can be told to fail deliveries to some followers
+#[derive(Clone, Default)]
+pub(super) struct RecordingTransport {
+    deliveries: Arc<Mutex<Vec<(NodeId, SeqNo)>>>,
+    failing: Arc<Mutex<BTreeSet<NodeId>>>,
+}
+
+impl RecordingTransport {
+    /// Fail every send to the given follower, along with any broadcast which includes it
+    pub(super) fn fail(&self, follower: u32) {
+        self.failing.lock().unwrap().insert(NodeId::from(follower));
+    }
+
+    fn is_failing(&self, target: &NodeId) -> bool {
+        self.failing.lock().unwrap().contains(target)
+    }
+
+    /// Take the `(follower, sequence)` of every delivery made since the last time they were taken
+    pub(super) fn take(&self) -> Vec<(NodeId, SeqNo)> {
+        std::mem::take(&mut *self.deliveries.lock().unwrap())
+    }
+
+    fn deliver(&self, message: &TestMessage, target: NodeId) {
+        self.deliveries.lock().unwrap().push((target, message.seq));
+    }
+}
+
+impl FollowerTransport<TestMessage> for RecordingTransport {
+    fn broadcast(&self, message: TestMessage, targets: Vec<NodeId>) -> std::result::Result<(), Vec<NodeId>> {
+        //A single bad target aborts the whole broadcast
+        if targets.iter().any(|target| self.is_failing(target)) {
+            return Err(targets);
+        }
+
+        for target in targets {
+            self.deliver(&message, target);
+        }
+
+        Ok(())
+    }
+
+    fn send(&self, message: TestMessage, target: NodeId, _flush: bool) -> Result<()> {
+        if self.is_failing(&target) {
+            return Err(Error::simple_with_msg(ErrorKind::Communication, "Follower is failing"));
+        }
+
+        self.deliver(&message, target);
+
+        Ok(())
+    }
+}
+
+pub(super) type TestInput = FollowerInput<TestView, TestMessage>;
+
+/// The follower handling of a replica, stepped manually and driven by a manual clock
+pub(super) struct TestFollowerHandling {
+    pub(super) events: ChannelSyncTx<TestInput>,
+    pub(super) control: FollowerControlHandle,
+    pub(super) forward: FollowerForwardHandle<TestMessage, RecordingTransport>,
+    pub(super) stepper: FollowerStepper<TestInput, RecordingTransport>,
+    pub(super) transport: RecordingTransport,
+    pub(super) clock: Arc<ManualClock>,
+}
+
+impl TestFollowerHandling {
+    pub(super) fn new(own_id: u32, view: TestView, config: FollowerHandlingConfig) -> Self {
+        let clock = Arc::new(ManualClock::new());
+        let transport = RecordingTransport::default();
+
+        let config = FollowerHandlingConfig { clock: clock.clone(), ..config };
+
+        let (events, control, forward, stepper) = FollowersFollowing::init_stepped_with_transport(
+            NodeId::from(own_id), config, transport.clone(), Box::new(classify), Some(view));
+
+        Self { events, control, forward, stepper, transport, clock }
+    }
+
+    /// Register the given followers, without batching
+    pub(super) fn register(&mut self, followers: &[u32]) {
+        for follower in nodes(followers) {
+            self.control.register_follower(follower, FollowerBatchingPreference::default()).unwrap();
+        }
+
+        self.step();
+    }
+
+    /// Deliver a consensus message of the given view and handle it
+    pub(super) fn consensus(&mut self, view: &TestView, message: Arc<ReadOnly<TestMessage>>) {
+        self.events.send(FollowerInput::Consensus(view.clone(), message)).unwrap();
+
+        self.step();
+    }
+
+    /// Deliver a sync message and handle it
+    pub(super) fn sync(&mut self, message: Arc<ReadOnly<TestMessage>>) {
+        self.events.send(FollowerInput::Sync(message)).unwrap();
+
+        self.step();
+    }
+
+    pub(super) fn step(&mut self) {
+        assert!(self.stepper.step(), "the follower handling has stopped");
+    }
+
+    /// Run a query against the control handle, stepping until it has been answered
+    pub(super) fn query<R: Send + 'static>(&mut self, query: impl FnOnce(&FollowerControlHandle) -> Result<R> + Send + 'static) -> R {
+        let control = self.control.clone();
+
+        self.while_stepping(move || query(&control)).unwrap()
+    }
+
+    /// Forward a message to explicit targets, stepping until the forward has been handled
+    pub(super) fn forward_to(&mut self, targets: &[u32], message: Arc<ReadOnly<TestMessage>>) -> Result<()> {
+        let forward = self.forward.clone();
+        let targets = nodes(targets);
+
+        self.while_stepping(move || forward.forward_to(targets, message))
+    }
+
+    /// Run a blocking call on another thread, stepping until it returns
+    fn while_stepping<R: Send + 'static>(&mut self, call: impl FnOnce() -> R + Send + 'static) -> R {
+        let handle = thread::spawn(call);
+
+        while !handle.is_finished() {
+            self.step();
+
+            thread::yield_now();
+        }
+
+        handle.join().unwrap()
+    }
+
+    /// The state of the follower handling, while it is still running
+    pub(super) fn state(&self) -> &FollowersFollowing<TestInput, RecordingTransport> {
+        match &self.stepper.state {
+            StepperState::Running(follower_handling) => follower_handling,
+            _ => panic!("the follower handling is not running"),
+        }
+    }
+}
@@ -39,12 +39,14 @@ use atlas_metrics::metrics::{metric_duration, metric_increment};
 use atlas_persistent_log::NoPersistentLog;
 
 use crate::config::ReplicaConfig;
-use crate::metric::{LOG_TRANSFER_PROCESS_TIME_ID, ORDERING_PROTOCOL_PROCESS_TIME_ID, REPLICA_INTERNAL_PROCESS_TIME_ID, REPLICA_ORDERED_RQS_PROCESSED_ID, REPLICA_TAKE_FROM_NETWORK_ID, STATE_TRANSFER_PROCESS_TIME_ID, TIMEOUT_PROCESS_TIME_ID};
+use crate::metric::{LOG_TRANSFER_PROCESS_TIME_ID, ORDERING_PROTOCOL_PROCESS_TIME_ID, REPLICA_INTERNAL_PROCESS_TIME_ID, REPLICA_ORDERED_RQS_PROCESSED_ID, REPLICA_TAKE_FROM_NETWORK_ID, STATE_TRANSFER_MESSAGE_PROCESS_TIME_ID, STATE_TRANSFER_POLL_TIME_ID, STATE_TRANSFER_PROCESS_TIME_ID, TIMEOUT_PROCESS_TIME_ID};
 use crate::persistent_log::SMRPersistentLog;
+use crate::server::state_request_throttle::StateRequestThrottle;
 
 
 pub mod client_replier;
 pub mod follower_handling;
+mod state_request_throttle;
 pub mod monolithic_server;
 mod divisible_state_server;
 // pub mod rq_finalizer;
@@ -76,6 +78,9 @@ pub struct Replica<RP, S, D, OP, ST, LT, NT, PL> where D: ApplicationData + 'sta
 
     quorum_reconfig_data: QuorumReconfig,
 
+    // Throttling of the requests for the latest state
+    state_request_throttle: StateRequestThrottle,
+
     // The ordering protocol, responsible for ordering requests
     ordering_protocol: OP,
     log_transfer_protocol: LT,
@@ -124,6 +129,9 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
             view,
             next_consensus_seq,
             db_path,
+            min_state_request_interval,
+            max_state_request_jitter,
+            clock,
             op_config,
             lt_config,
             pl_config,
@@ -215,6 +223,7 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
 // We start with the state transfer protocol to make sure everything is up to date
             replica_phase: state_transfer,
             quorum_reconfig_data: QuorumReconfig { node_pending_join: None },
+            state_request_throttle: StateRequestThrottle::new(clock, min_state_request_interval, max_state_request_jitter),
             ordering_protocol,
             log_transfer_protocol,
             rq_pre_processor,
@@ -248,6 +257,12 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
 
         metric_duration(REPLICA_INTERNAL_PROCESS_TIME_ID, now.elapsed());
 
+        if let ReplicaPhase::StateTransferProtocol { .. } = &self.replica_phase {
+            if self.state_request_throttle.should_run_deferred() {
+                self.request_latest_state(state_transfer)?;
+            }
+        }
+
         match &self.replica_phase {
             ReplicaPhase::OrderingProtocol => {
                 let poll_res = self.ordering_protocol.poll();
@@ -378,6 +393,9 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
                 let message = self.node.node_incoming_rq_handling().receive_from_replicas(Some(REPLICA_WAIT_TIME)).unwrap();
 
                 if let Some(message) = message {
+                    //Only the handling of the polled message is timed, not the wait for it
+                    let poll_start = Instant::now();
+
                     let (header, message) = message.into_inner();
 
                     match message {
@@ -389,6 +407,8 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
 
                             let result = state_transfer.process_message(self.ordering_protocol.view(), StoredMessage::new(header, state_transfer_msg))?;
 
+                            metric_duration(STATE_TRANSFER_MESSAGE_PROCESS_TIME_ID, start.elapsed());
+
                             match result {
                                 STResult::StateTransferRunning => {}
                                 STResult::StateTransferReady => {
@@ -436,6 +456,8 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
                         }
                         _ => {}
                     }
+
+                    metric_duration(STATE_TRANSFER_POLL_TIME_ID, poll_start.elapsed());
                 }
             }
         }
@@ -744,7 +766,7 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
         }
 
 // Start by requesting the current state from neighbour replicas
-        state_transfer.request_latest_state(self.ordering_protocol.view())?;
+        self.request_latest_state(state_transfer)?;
         self.log_transfer_protocol.request_latest_log(&mut self.ordering_protocol)?;
 
         Ok(())
@@ -763,13 +785,26 @@ impl<RP, S, D, OP, ST, LT, NT, PL> Replica<RP, S, D, OP, ST, LT, NT, PL>
 
                 *state_transfer = None;
 
-                state_transfer_p.request_latest_state(self.ordering_protocol.view())?;
+                self.request_latest_state(state_transfer_p)?;
             }
         }
 
         Ok(())
     }
 
+    /// Request the latest state from the state transfer protocol, unless the last request
+    /// was made too recently, in which case this request is deferred until the
+    /// minimum interval has elapsed
+    fn request_latest_state(&mut self, state_transfer: &mut ST) -> Result<()> {
+        if !self.state_request_throttle.attempt_request() {
+            debug!("{:?} // Deferring request for the latest state, either for its jitter or as the last one was too recent", self.id());
+
+            return Ok(());
+        }
+
+        state_transfer.request_latest_state(self.ordering_protocol.view())
+    }
+
     /// Runs the log transfer protocol on this replica
     fn run_log_transfer_protocol(&mut self, state_transfer: &mut ST) -> Result<()> {
         info!("{:?} // Running log transfer protocol. {:?}", NetworkNode::id(&*self.node), self.replica_phase);
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::clock::Clock;
+
+/// Throttles the requests for the latest state made to the state transfer protocol
+pub struct StateRequestThrottle {
+    clock: Arc<dyn Clock>,
+    min_interval: Duration,
+    last_request: Option<Instant>,
+    deferred: bool,
+    max_jitter: Duration,
+    /// The end of the random delay applied to the pending request
+    not_before: Option<Instant>,
+}
+
+impl StateRequestThrottle {
+    pub(super) fn new(clock: Arc<dyn Clock>, min_interval: Duration, max_jitter: Duration) -> Self {
+        Self {
+            clock,
+            min_interval,
+            last_request: None,
+            deferred: false,
+            max_jitter,
+            not_before: None,
+        }
+    }
+
+    /// Can a request be made right now? If not, the request is marked as deferred.
+    /// New requests are first delayed by a random jitter, if one is configured
+    pub(super) fn attempt_request(&mut self) -> bool {
+        match self.not_before {
+            Some(not_before) if self.clock.now() < not_before => {
+                self.deferred = true;
+
+                return false;
+            }
+            None if !self.max_jitter.is_zero() => {
+                let jitter = rand::thread_rng().gen_range(Duration::ZERO..=self.max_jitter);
+
+                self.not_before = Some(self.clock.now() + jitter);
+                self.deferred = true;
+
+                return false;
+            }
+            _ => {}
+        }
+
+        let allowed = self.last_request
+            .map_or(true, |last| self.clock.elapsed_since(last) >= self.min_interval);
+
+        if allowed {
+            self.last_request = Some(self.clock.now());
+            self.deferred = false;
+            self.not_before = None;
+        } else {
+            self.deferred = true;
+        }
+
+        allowed
+    }
+
+    /// Is there a deferred request which can now be made?
+    pub(super) fn should_run_deferred(&self) -> bool {
+        self.deferred
+            && self.not_before.map_or(true, |not_before| self.clock.now() >= not_before)
+            && self.last_request.map_or(true, |last| self.clock.elapsed_since(last) >= self.min_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::ManualClock;
+
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    fn throttle(min_interval: Duration, max_jitter: Duration) -> (StateRequestThrottle, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+
+        (StateRequestThrottle::new(clock.clone(), min_interval, max_jitter), clock)
+    }
+
+    #[test]
+    fn requests_inside_the_interval_are_deferred() {
+        let (mut throttle, clock) = throttle(INTERVAL, Duration::ZERO);
+
+        assert!(throttle.attempt_request());
+
+        clock.advance(INTERVAL / 2);
+
+        assert!(!throttle.attempt_request());
+        assert!(!throttle.should_run_deferred());
+    }
+
+    #[test]
+    fn deferred_requests_run_once_the_interval_has_passed() {
+        let (mut throttle, clock) = throttle(INTERVAL, Duration::ZERO);
+
+        assert!(throttle.attempt_request());
+        assert!(!throttle.attempt_request());
+
+        clock.advance(INTERVAL);
+
+        assert!(throttle.should_run_deferred());
+        assert!(throttle.attempt_request());
+
+        //Nothing is left deferred once it has run
+        assert!(!throttle.should_run_deferred());
+    }
+
+    #[test]
+    fn a_zero_interval_does_not_throttle() {
+        let (mut throttle, _clock) = throttle(Duration::ZERO, Duration::ZERO);
+
+        for _ in 0..3 {
+            assert!(throttle.attempt_request());
+            assert!(!throttle.should_run_deferred());
+        }
+    }
+
+    #[test]
+    fn the_interval_is_measured_by_the_injected_clock() {
+        let interval = Duration::from_millis(10);
+
+        let (mut throttle, clock) = throttle(interval, Duration::ZERO);
+
+        assert!(throttle.attempt_request());
+        assert!(!throttle.attempt_request());
+
+        //Wall time passing does not end the interval
+        std::thread::sleep(interval * 2);
+
+        assert!(!throttle.should_run_deferred());
+
+        clock.advance(interval);
+
+        assert!(throttle.should_run_deferred());
+    }
+
+    #[test]
+    fn requests_are_delayed_by_at_most_the_max_jitter() {
+        let max_jitter = Duration::from_millis(200);
+
+        let (mut throttle, clock) = throttle(INTERVAL, max_jitter);
+
+        //The first request is only delayed by the jitter
+        assert!(!throttle.attempt_request());
+
+        clock.advance(max_jitter);
+
+        assert!(throttle.should_run_deferred());
+        assert!(throttle.attempt_request());
+
+        //Later ones wait for the interval, and then at most the jitter
+        for _ in 0..50 {
+            assert!(!throttle.attempt_request());
+
+            clock.advance(INTERVAL - Duration::from_nanos(1));
+
+            assert!(!throttle.should_run_deferred());
+
+            clock.advance(max_jitter + Duration::from_nanos(1));
+
+            assert!(throttle.should_run_deferred());
+            assert!(throttle.attempt_request());
+        }
+    }
+
+    #[test]
+    fn without_jitter_requests_are_not_delayed() {
+        let (mut throttle, clock) = throttle(INTERVAL, Duration::ZERO);
+
+        assert!(throttle.attempt_request());
+        assert!(!throttle.attempt_request());
+
+        clock.advance(INTERVAL);
+
+        assert!(throttle.attempt_request());
+    }
+}